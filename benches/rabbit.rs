@@ -0,0 +1,219 @@
+//! Benchmarks against ChaCha20 from RustCrypto.
+//!
+//! Time is reported with throughput in GB/s. On x86_64 the same benchmarks also run
+//! under `cycles/`, measured with `rdtsc`, with throughput in cycles per byte. `rdtsc`
+//! counts reference cycles, so disable frequency scaling (turbo) for meaningful
+//! numbers.
+
+extern crate chacha20;
+#[macro_use]
+extern crate criterion;
+extern crate rabbit;
+
+use std::hint::black_box;
+use std::io::{self, Write};
+
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::ChaCha20;
+use criterion::measurement::Measurement;
+use criterion::{BenchmarkId, Criterion, Throughput};
+use rabbit::{InitVec, Key, Rabbit, RabbitKey, Stream};
+
+const SIZES: [usize; 3] = [64, 1024, 1 << 20];
+const SMALL: [usize; 3] = [16, 64, 256];
+
+fn key() -> Key {
+    Key::from([0x42; 16])
+}
+
+fn iv() -> InitVec {
+    InitVec::from(0x0123456789ABCDEF)
+}
+
+fn chacha() -> ChaCha20 {
+    ChaCha20::new(&[0x42; 32].into(), &[0x24; 12].into())
+}
+
+fn setup<M: Measurement>(c: &mut Criterion<M>, prefix: &str) {
+    let mut group = c.benchmark_group(format!("{}setup", prefix));
+    let key = key();
+    let iv = iv();
+    group.bench_function("rabbit/new", |b| b.iter(|| Rabbit::new(black_box(&key))));
+    let mut rabbit = Rabbit::new(&key);
+    group.bench_function("rabbit/reinit", |b| b.iter(|| rabbit.reinit(black_box(&iv))));
+    group.bench_function("rabbit/new_iv", |b| {
+        b.iter(|| Rabbit::new_iv(black_box(&key), black_box(&iv)))
+    });
+    let rabbit_key = RabbitKey::new(&key);
+    group.bench_function("rabbit/RabbitKey::rabbit_iv", |b| {
+        b.iter(|| rabbit_key.rabbit_iv(black_box(&iv)))
+    });
+    group.bench_function("chacha20/new", |b| b.iter(|| black_box(chacha())));
+    group.finish();
+}
+
+/// Latency of a fresh message: IV setup followed by encryption.
+fn small<M: Measurement>(c: &mut Criterion<M>, prefix: &str) {
+    let mut group = c.benchmark_group(format!("{}small", prefix));
+    let mut rabbit = Rabbit::new(&key());
+    let iv = iv();
+    for &size in SMALL.iter() {
+        let mut data = vec![0; size];
+        group.throughput(Throughput::BytesDecimal(size as u64));
+        group.bench_function(BenchmarkId::new("rabbit/reinit+encrypt_inplace", size), |b| {
+            b.iter(|| {
+                rabbit.reinit(black_box(&iv));
+                rabbit.encrypt_inplace(&mut data);
+            })
+        });
+        group.bench_function(BenchmarkId::new("chacha20/new+apply_keystream", size), |b| {
+            b.iter(|| chacha().apply_keystream(&mut data))
+        });
+    }
+    group.finish();
+}
+
+fn bulk<M: Measurement>(c: &mut Criterion<M>, prefix: &str) {
+    let mut group = c.benchmark_group(format!("{}bulk", prefix));
+    for &size in SIZES.iter() {
+        let data = vec![0; size];
+        let mut buf = vec![0; size];
+        let mut rabbit = Rabbit::new_iv(&key(), &iv());
+        group.throughput(Throughput::BytesDecimal(size as u64));
+        group.bench_function(BenchmarkId::new("rabbit/encrypt", size), |b| {
+            b.iter(|| rabbit.encrypt(black_box(&data), &mut buf))
+        });
+        group.bench_function(BenchmarkId::new("rabbit/encrypt_inplace", size), |b| {
+            b.iter(|| rabbit.encrypt_inplace(black_box(&mut buf)))
+        });
+        let mut stream = Stream::new(Rabbit::new_iv(&key(), &iv()), io::sink());
+        group.bench_function(BenchmarkId::new("rabbit/stream", size), |b| {
+            b.iter(|| stream.write_all(black_box(&data)).unwrap())
+        });
+        let mut chacha = chacha();
+        group.bench_function(BenchmarkId::new("chacha20/apply_keystream", size), |b| {
+            b.iter(|| chacha.apply_keystream(black_box(&mut buf)))
+        });
+    }
+    group.finish();
+}
+
+/// Sequential against `rayon` parallel encryption of large buffers.
+#[cfg(feature = "rayon")]
+fn parallel<M: Measurement>(c: &mut Criterion<M>, prefix: &str) {
+    use rabbit::parallel::ParallelRabbit;
+
+    let mut group = c.benchmark_group(format!("{}parallel", prefix));
+    let size = 16 << 20;
+    let mut buf = vec![0; size];
+    let mut rabbit = Rabbit::new_iv(&key(), &iv());
+    let cipher = ParallelRabbit::new(&key(), &iv());
+    group.throughput(Throughput::BytesDecimal(size as u64));
+    group.bench_function(BenchmarkId::new("rabbit/encrypt_inplace", size), |b| {
+        b.iter(|| rabbit.encrypt_inplace(black_box(&mut buf)))
+    });
+    group.bench_function(BenchmarkId::new("parallel/encrypt_inplace", size), |b| {
+        b.iter(|| cipher.encrypt_inplace(black_box(&mut buf)))
+    });
+    group.finish();
+}
+
+#[cfg(not(feature = "rayon"))]
+fn parallel<M: Measurement>(_: &mut Criterion<M>, _: &str) {}
+
+fn time(c: &mut Criterion) {
+    setup(c, "");
+    small(c, "");
+    bulk(c, "");
+    parallel(c, "");
+}
+
+#[cfg(target_arch = "x86_64")]
+mod cycles {
+    use criterion::measurement::{Measurement, ValueFormatter};
+    use criterion::{Criterion, Throughput};
+
+    /// Elapsed time stamp counter cycles.
+    pub struct Cycles;
+
+    impl Measurement for Cycles {
+        type Intermediate = u64;
+        type Value = u64;
+
+        fn start(&self) -> u64 {
+            rdtsc()
+        }
+
+        fn end(&self, start: u64) -> u64 {
+            rdtsc().wrapping_sub(start)
+        }
+
+        fn add(&self, v1: &u64, v2: &u64) -> u64 {
+            v1 + v2
+        }
+
+        fn zero(&self) -> u64 {
+            0
+        }
+
+        fn to_f64(&self, value: &u64) -> f64 {
+            *value as f64
+        }
+
+        fn formatter(&self) -> &dyn ValueFormatter {
+            &CyclesFormatter
+        }
+    }
+
+    fn rdtsc() -> u64 {
+        unsafe { ::std::arch::x86_64::_rdtsc() }
+    }
+
+    struct CyclesFormatter;
+
+    impl ValueFormatter for CyclesFormatter {
+        fn scale_values(&self, _typical: f64, _values: &mut [f64]) -> &'static str {
+            "cycles"
+        }
+
+        fn scale_throughputs(&self, _typical: f64, throughput: &Throughput,
+                             values: &mut [f64]) -> &'static str {
+            let count = match *throughput {
+                Throughput::Bytes(n) | Throughput::BytesDecimal(n) => n,
+                Throughput::Elements(n) => n,
+            };
+            for value in values.iter_mut() {
+                *value /= count as f64;
+            }
+            match *throughput {
+                Throughput::Elements(_) => "cycles/elem",
+                _ => "cycles/byte",
+            }
+        }
+
+        fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+            "cycles"
+        }
+    }
+
+    pub fn run(c: &mut Criterion<Cycles>) {
+        super::setup(c, "cycles/");
+        super::small(c, "cycles/");
+        super::bulk(c, "cycles/");
+        super::parallel(c, "cycles/");
+    }
+}
+
+criterion_group!(time_benches, time);
+
+#[cfg(target_arch = "x86_64")]
+criterion_group! {
+    name = cycle_benches;
+    config = Criterion::default().with_measurement(cycles::Cycles);
+    targets = cycles::run
+}
+
+#[cfg(target_arch = "x86_64")]
+criterion_main!(time_benches, cycle_benches);
+#[cfg(not(target_arch = "x86_64"))]
+criterion_main!(time_benches);
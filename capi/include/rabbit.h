@@ -0,0 +1,92 @@
+/* Generated with cbindgen from capi/src/lib.rs, do not edit. */
+
+#ifndef RABBIT_H
+#define RABBIT_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+// Success.
+#define RABBIT_OK 0
+
+// Required pointer argument is NULL.
+#define RABBIT_ERROR_NULL_POINTER -1
+
+// Key is not 16 bytes or IV is not 8 bytes.
+#define RABBIT_ERROR_INVALID_LENGTH -2
+
+// Internal error (Rust panic), the handle must not be used further.
+#define RABBIT_ERROR_INTERNAL -3
+
+// Key length in bytes.
+#define RABBIT_KEY_LEN 16
+
+// IV length in bytes.
+#define RABBIT_IV_LEN 8
+
+// Opaque cipher handle.
+typedef struct Rabbit Rabbit;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Creates cipher keyed with `key` of `key_len` (16) bytes, without IV setup.
+// Stores handle to `*out`, or NULL on error.
+//
+// # Safety
+//
+// `key` must point to `key_len` readable bytes, `out` must be writable.
+int32_t rabbit_new(const uint8_t *key, size_t key_len, struct Rabbit **out);
+
+// Creates cipher keyed with `key` of `key_len` (16) bytes and IV `iv` of `iv_len` (8)
+// bytes. Stores handle to `*out`, or NULL on error.
+//
+// # Safety
+//
+// `key` and `iv` must point to `key_len` and `iv_len` readable bytes, `out` must be
+// writable.
+int32_t rabbit_new_iv(const uint8_t *key,
+                      size_t key_len,
+                      const uint8_t *iv,
+                      size_t iv_len,
+                      struct Rabbit **out);
+
+// Restarts keystream from key setup followed by setup of `iv` of `iv_len` (8) bytes.
+//
+// # Safety
+//
+// `rabbit` must be a live handle, `iv` must point to `iv_len` readable bytes.
+int32_t rabbit_reinit(struct Rabbit *rabbit, const uint8_t *iv, size_t iv_len);
+
+// Restarts current keystream (of `rabbit_new`, or of the last IV setup).
+//
+// # Safety
+//
+// `rabbit` must be a live handle.
+int32_t rabbit_reset(struct Rabbit *rabbit);
+
+// Encrypts or decrypts `len` bytes of `input` into `output`. Buffers may be the same
+// or overlap. Pointers may be NULL if `len` is zero.
+//
+// # Safety
+//
+// `rabbit` must be a live handle, `input` and `output` must point to `len` readable
+// and writable bytes respectively.
+int32_t rabbit_apply(struct Rabbit *rabbit, const uint8_t *input, uint8_t *output, size_t len);
+
+// Releases handle, NULL is ignored.
+//
+// # Safety
+//
+// `rabbit` must be NULL or a live handle, which is not used afterwards.
+void rabbit_free(struct Rabbit *rabbit);
+
+// Static description of status `code`.
+const char *rabbit_error_message(int32_t code);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* RABBIT_H */
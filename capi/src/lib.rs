@@ -0,0 +1,259 @@
+//! C API for the Rabbit stream cipher, see `include/rabbit.h`.
+//!
+//! Ciphers are opaque handles created by `rabbit_new` or `rabbit_new_iv` and released
+//! with `rabbit_free`. Every other function returns a status code, `RABBIT_OK` on
+//! success, and never panics across the boundary.
+
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use rabbit::{InitVec, Key};
+
+/// Success.
+pub const RABBIT_OK: i32 = 0;
+/// Required pointer argument is NULL.
+pub const RABBIT_ERROR_NULL_POINTER: i32 = -1;
+/// Key is not 16 bytes or IV is not 8 bytes.
+pub const RABBIT_ERROR_INVALID_LENGTH: i32 = -2;
+/// Internal error (Rust panic), the handle must not be used further.
+pub const RABBIT_ERROR_INTERNAL: i32 = -3;
+
+/// Key length in bytes.
+pub const RABBIT_KEY_LEN: usize = 16;
+/// IV length in bytes.
+pub const RABBIT_IV_LEN: usize = 8;
+
+/// Opaque cipher handle.
+pub struct Rabbit {
+    inner: rabbit::Rabbit,
+}
+
+/// Runs `f`, converting a panic into `RABBIT_ERROR_INTERNAL`.
+fn guard<F: FnOnce() -> i32>(f: F) -> i32 {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(RABBIT_ERROR_INTERNAL)
+}
+
+/// Reads `len` bytes at `ptr`, which must be exactly `expected` bytes long.
+unsafe fn bytes<'a>(ptr: *const u8, len: usize, expected: usize) -> Result<&'a [u8], i32> {
+    if ptr.is_null() {
+        return Err(RABBIT_ERROR_NULL_POINTER);
+    }
+    if len != expected {
+        return Err(RABBIT_ERROR_INVALID_LENGTH);
+    }
+    Ok(slice::from_raw_parts(ptr, len))
+}
+
+unsafe fn create(
+    key: *const u8,
+    key_len: usize,
+    iv: Option<(*const u8, usize)>,
+    out: *mut *mut Rabbit,
+) -> i32 {
+    if out.is_null() {
+        return RABBIT_ERROR_NULL_POINTER;
+    }
+    *out = ptr::null_mut();
+    guard(|| {
+        let key = match bytes(key, key_len, RABBIT_KEY_LEN) {
+            Ok(key) => Key::from(key),
+            Err(code) => return code,
+        };
+        let inner = match iv {
+            Some((iv, iv_len)) => match bytes(iv, iv_len, RABBIT_IV_LEN) {
+                Ok(iv) => rabbit::Rabbit::new_iv(&key, &InitVec::from(iv)),
+                Err(code) => return code,
+            },
+            None => rabbit::Rabbit::new(&key),
+        };
+        *out = Box::into_raw(Box::new(Rabbit { inner }));
+        RABBIT_OK
+    })
+}
+
+/// Creates cipher keyed with `key` of `key_len` (16) bytes, without IV setup.
+/// Stores handle to `*out`, or NULL on error.
+///
+/// # Safety
+///
+/// `key` must point to `key_len` readable bytes, `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn rabbit_new(key: *const u8, key_len: usize, out: *mut *mut Rabbit) -> i32 {
+    create(key, key_len, None, out)
+}
+
+/// Creates cipher keyed with `key` of `key_len` (16) bytes and IV `iv` of `iv_len` (8)
+/// bytes. Stores handle to `*out`, or NULL on error.
+///
+/// # Safety
+///
+/// `key` and `iv` must point to `key_len` and `iv_len` readable bytes, `out` must be
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn rabbit_new_iv(
+    key: *const u8,
+    key_len: usize,
+    iv: *const u8,
+    iv_len: usize,
+    out: *mut *mut Rabbit,
+) -> i32 {
+    create(key, key_len, Some((iv, iv_len)), out)
+}
+
+/// Restarts keystream from key setup followed by setup of `iv` of `iv_len` (8) bytes.
+///
+/// # Safety
+///
+/// `rabbit` must be a live handle, `iv` must point to `iv_len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rabbit_reinit(rabbit: *mut Rabbit, iv: *const u8, iv_len: usize) -> i32 {
+    if rabbit.is_null() {
+        return RABBIT_ERROR_NULL_POINTER;
+    }
+    guard(|| match bytes(iv, iv_len, RABBIT_IV_LEN) {
+        Ok(iv) => {
+            (*rabbit).inner.reinit(&InitVec::from(iv));
+            RABBIT_OK
+        }
+        Err(code) => code,
+    })
+}
+
+/// Restarts current keystream (of `rabbit_new`, or of the last IV setup).
+///
+/// # Safety
+///
+/// `rabbit` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn rabbit_reset(rabbit: *mut Rabbit) -> i32 {
+    if rabbit.is_null() {
+        return RABBIT_ERROR_NULL_POINTER;
+    }
+    guard(|| {
+        (*rabbit).inner.reset();
+        RABBIT_OK
+    })
+}
+
+/// Encrypts or decrypts `len` bytes of `input` into `output`. Buffers may be the same
+/// or overlap. Pointers may be NULL if `len` is zero.
+///
+/// # Safety
+///
+/// `rabbit` must be a live handle, `input` and `output` must point to `len` readable
+/// and writable bytes respectively.
+#[no_mangle]
+pub unsafe extern "C" fn rabbit_apply(
+    rabbit: *mut Rabbit,
+    input: *const u8,
+    output: *mut u8,
+    len: usize,
+) -> i32 {
+    if rabbit.is_null() || (len > 0 && (input.is_null() || output.is_null())) {
+        return RABBIT_ERROR_NULL_POINTER;
+    }
+    if len == 0 {
+        return RABBIT_OK;
+    }
+    guard(|| {
+        ptr::copy(input, output, len);
+        (*rabbit).inner.encrypt_inplace(slice::from_raw_parts_mut(output, len));
+        RABBIT_OK
+    })
+}
+
+/// Releases handle, NULL is ignored.
+///
+/// # Safety
+///
+/// `rabbit` must be NULL or a live handle, which is not used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rabbit_free(rabbit: *mut Rabbit) {
+    if !rabbit.is_null() {
+        drop(Box::from_raw(rabbit));
+    }
+}
+
+/// Static description of status `code`.
+#[no_mangle]
+pub extern "C" fn rabbit_error_message(code: i32) -> *const c_char {
+    let message: &'static [u8] = match code {
+        RABBIT_OK => b"success\0",
+        RABBIT_ERROR_NULL_POINTER => b"null pointer\0",
+        RABBIT_ERROR_INVALID_LENGTH => b"invalid key or IV length\0",
+        RABBIT_ERROR_INTERNAL => b"internal error\0",
+        _ => b"unknown error\0",
+    };
+    message.as_ptr() as *const c_char
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::CStr;
+    use std::ptr;
+
+    use super::*;
+
+    #[test]
+    fn rejects_bad_arguments() {
+        let key = [0u8; 16];
+        let mut rabbit: *mut Rabbit = ptr::null_mut();
+        unsafe {
+            assert_eq!(rabbit_new(key.as_ptr(), 15, &mut rabbit), RABBIT_ERROR_INVALID_LENGTH);
+            assert!(rabbit.is_null());
+            assert_eq!(rabbit_new(ptr::null(), 16, &mut rabbit), RABBIT_ERROR_NULL_POINTER);
+            assert_eq!(rabbit_new(key.as_ptr(), 16, ptr::null_mut()), RABBIT_ERROR_NULL_POINTER);
+            assert_eq!(
+                rabbit_new_iv(key.as_ptr(), 16, key.as_ptr(), 9, &mut rabbit),
+                RABBIT_ERROR_INVALID_LENGTH
+            );
+            assert!(rabbit.is_null());
+
+            assert_eq!(rabbit_new(key.as_ptr(), 16, &mut rabbit), RABBIT_OK);
+            assert!(!rabbit.is_null());
+            assert_eq!(rabbit_reinit(rabbit, key.as_ptr(), 16), RABBIT_ERROR_INVALID_LENGTH);
+            assert_eq!(rabbit_reinit(ptr::null_mut(), key.as_ptr(), 8), RABBIT_ERROR_NULL_POINTER);
+            assert_eq!(rabbit_reset(ptr::null_mut()), RABBIT_ERROR_NULL_POINTER);
+            assert_eq!(rabbit_apply(rabbit, ptr::null(), ptr::null_mut(), 0), RABBIT_OK);
+            let mut buf = [0u8; 4];
+            assert_eq!(rabbit_apply(rabbit, ptr::null(), buf.as_mut_ptr(), 4), RABBIT_ERROR_NULL_POINTER);
+            rabbit_free(rabbit);
+            rabbit_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn apply_matches_library() {
+        let key = [7u8; 16];
+        let iv = [9u8; 8];
+        let mut expected = [0u8; 100];
+        rabbit::Rabbit::new_iv(&Key::from(key), &InitVec::from(iv)).encrypt_inplace(&mut expected);
+
+        unsafe {
+            let mut rabbit: *mut Rabbit = ptr::null_mut();
+            assert_eq!(rabbit_new_iv(key.as_ptr(), 16, iv.as_ptr(), 8, &mut rabbit), RABBIT_OK);
+            let mut buf = [0u8; 100];
+            // In place, then overlapping by one byte.
+            assert_eq!(rabbit_apply(rabbit, buf.as_ptr(), buf.as_mut_ptr(), 50), RABBIT_OK);
+            let input = buf.as_ptr().add(51);
+            assert_eq!(rabbit_apply(rabbit, input, buf.as_mut_ptr().add(50), 49), RABBIT_OK);
+            assert_eq!(&buf[..99], &expected[..99]);
+
+            assert_eq!(rabbit_reset(rabbit), RABBIT_OK);
+            let zeros = [0u8; 100];
+            assert_eq!(rabbit_apply(rabbit, zeros.as_ptr(), buf.as_mut_ptr(), 100), RABBIT_OK);
+            assert_eq!(buf, expected);
+            rabbit_free(rabbit);
+        }
+    }
+
+    #[test]
+    fn error_messages() {
+        let message = |code| unsafe { CStr::from_ptr(rabbit_error_message(code)) };
+        assert_eq!(message(RABBIT_OK).to_str().unwrap(), "success");
+        assert_eq!(message(RABBIT_ERROR_INVALID_LENGTH).to_str().unwrap(), "invalid key or IV length");
+        assert_eq!(message(42).to_str().unwrap(), "unknown error");
+    }
+}
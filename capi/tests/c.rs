@@ -0,0 +1,74 @@
+//! Compiles and runs C programs in `tests/c` against the static library.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Builds `librabbit_capi.a` (`cargo test` only builds the rlib) into the profile
+/// directory of this test and returns that directory.
+fn lib_dir() -> PathBuf {
+    let exe = env::current_exe().unwrap();
+    let profile_dir = exe.parent().and_then(Path::parent).unwrap().to_path_buf();
+    let target_dir = profile_dir.parent().unwrap();
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let mut build = Command::new(cargo);
+    build
+        .args(["build", "--quiet", "--lib", "-p", "rabbit-capi", "--target-dir"])
+        .arg(target_dir);
+    if profile_dir.ends_with("release") {
+        build.arg("--release");
+    }
+    assert!(build.status().unwrap().success(), "building static library");
+    profile_dir
+}
+
+/// Compiles `tests/c/<name>.c` as C (`cpp == false`) or C++ and runs it.
+fn compile_and_run(name: &str, cpp: bool) {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let source = crate_dir.join("tests").join("c").join(format!("{}.c", name));
+    let lib_dir = lib_dir();
+    let exe = lib_dir.join(format!("c-{}{}", name, if cpp { "-cpp" } else { "" }));
+    let compiler = if cpp {
+        env::var("CXX").unwrap_or_else(|_| "c++".to_string())
+    } else {
+        env::var("CC").unwrap_or_else(|_| "cc".to_string())
+    };
+
+    let mut cc = Command::new(&compiler);
+    if cpp {
+        cc.args(["-x", "c++", "-std=c++11"]);
+    } else {
+        cc.arg("-std=c99");
+    }
+    cc.args(["-Wall", "-Wextra", "-Werror"])
+        .arg("-I")
+        .arg(crate_dir.join("include"))
+        .arg(&source)
+        .args(if cpp { &["-x", "none"][..] } else { &[][..] })
+        .arg(lib_dir.join("librabbit_capi.a"))
+        .arg("-o")
+        .arg(&exe);
+    if cfg!(target_os = "linux") {
+        cc.args(["-lpthread", "-ldl", "-lm"]);
+    }
+    let status = cc.status().unwrap_or_else(|err| panic!("{}: {}", compiler, err));
+    assert!(status.success(), "compiling {}", source.display());
+
+    let output = Command::new(&exe).output().unwrap();
+    assert!(
+        output.status.success(),
+        "{} failed:\n{}",
+        name,
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn rfc4503() {
+    compile_and_run("rfc4503", false);
+}
+
+#[test]
+fn rfc4503_as_cpp() {
+    compile_and_run("rfc4503", true);
+}
@@ -0,0 +1,30 @@
+//! `include/rabbit.h` must match the header generated by cbindgen. Run with
+//! `RABBIT_UPDATE_HEADER=1` to regenerate it.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn header_is_up_to_date() {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml")).unwrap();
+    let mut generated = Vec::new();
+    cbindgen::Builder::new()
+        .with_crate(crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("cbindgen failed")
+        .write(&mut generated);
+
+    let path = crate_dir.join("include").join("rabbit.h");
+    if env::var_os("RABBIT_UPDATE_HEADER").is_some() {
+        fs::write(&path, &generated).unwrap();
+    }
+    let committed = fs::read(&path).unwrap_or_default();
+    assert!(
+        committed == generated,
+        "{} is out of date, rerun with RABBIT_UPDATE_HEADER=1",
+        path.display()
+    );
+}
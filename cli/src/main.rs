@@ -0,0 +1,318 @@
+//! `rabbit` command-line tool.
+//!
+//! Raw mode (default) is plain Rabbit keystream XOR, keyed with `Rabbit::new_iv` or, with
+//! `--no-iv`, `Rabbit::new`. Raw encryption requires one of them: without IV every input
+//! encrypted with the key reuses keystream. Authenticated mode (`--auth`) reads and writes
+//! `rabbit::container` files with chunked authentication tags.
+
+use std::env;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use clap::{Args, Parser, Subcommand};
+use rabbit::container::{self, FileDecryptor, FileEncryptor, Header, DEFAULT_CHUNK_SIZE};
+use rabbit::{InitVec, Key, Rabbit, Stream};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Keystream bytes generated and written at once by `keystream`.
+const KEYSTREAM_CHUNK: usize = 64 * 1024;
+
+#[derive(Parser)]
+#[command(name = "rabbit", version, about = "Rabbit stream cipher (RFC 4503) tool")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Encrypts input
+    Encrypt(Crypt),
+    /// Decrypts input
+    Decrypt(Crypt),
+    /// Prints random key (or IV) as hex
+    Keygen {
+        /// Generate 64-bit IV instead of 128-bit key
+        #[arg(long)]
+        iv: bool,
+    },
+    /// Writes raw keystream
+    Keystream {
+        #[command(flatten)]
+        key: KeyArgs,
+        #[command(flatten)]
+        iv: IvArgs,
+        /// Number of keystream bytes to write
+        #[arg(long)]
+        length: u64,
+        /// Number of keystream bytes to skip
+        #[arg(long, default_value_t = 0)]
+        offset: u64,
+        /// Write hex instead of raw bytes
+        #[arg(long)]
+        hex: bool,
+        /// Output file, stdout if omitted or `-`
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+struct KeyArgs {
+    /// Key as 32 hex digits
+    #[arg(long, value_name = "HEX")]
+    key: Option<String>,
+    /// File with key as 16 raw bytes or 32 hex digits
+    #[arg(long, value_name = "PATH")]
+    key_file: Option<PathBuf>,
+    /// Environment variable with key as 32 hex digits
+    #[arg(long, value_name = "VAR")]
+    key_env: Option<String>,
+}
+
+#[derive(Args)]
+#[group(required = false, multiple = false)]
+struct IvArgs {
+    /// IV as 16 hex digits
+    #[arg(long, value_name = "HEX")]
+    iv: Option<String>,
+    /// File with IV as 8 raw bytes or 16 hex digits
+    #[arg(long, value_name = "PATH")]
+    iv_file: Option<PathBuf>,
+    /// Environment variable with IV as 16 hex digits
+    #[arg(long, value_name = "VAR")]
+    iv_env: Option<String>,
+}
+
+#[derive(Args)]
+struct Crypt {
+    #[command(flatten)]
+    key: KeyArgs,
+    #[command(flatten)]
+    iv: IvArgs,
+    /// Use authenticated container format (random IV unless given, IV is stored in header)
+    #[arg(long)]
+    auth: bool,
+    /// Raw mode without IV: keystream depends on key only, never reuse the key
+    #[arg(long, conflicts_with_all = ["iv", "iv_file", "iv_env", "auth"])]
+    no_iv: bool,
+    /// Key id stored in (or expected from) container header, 16 hex digits
+    #[arg(long, value_name = "HEX", requires = "auth")]
+    key_id: Option<String>,
+    /// Container chunk size in bytes
+    #[arg(long, value_name = "BYTES", default_value_t = DEFAULT_CHUNK_SIZE)]
+    chunk_size: u32,
+    /// Input file, stdin if omitted or `-`
+    #[arg(short, long)]
+    input: Option<PathBuf>,
+    /// Output file, stdout if omitted or `-`
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(err) = run(cli.command) {
+        eprintln!("rabbit: {}", err);
+        process::exit(1);
+    }
+}
+
+fn run(command: Command) -> Result<()> {
+    match command {
+        Command::Encrypt(args) => with_output(&args.output, |output| encrypt(&args, output)),
+        Command::Decrypt(args) => with_output(&args.output, |output| decrypt(&args, output)),
+        Command::Keygen { iv } => {
+            let mut bytes = vec![0; if iv { 8 } else { 16 }];
+            getrandom::fill(&mut bytes).map_err(|err| format!("random: {}", err))?;
+            println!("{}", hex::encode(bytes));
+            Ok(())
+        }
+        Command::Keystream { key, iv, length, offset, hex, output } => {
+            let mut rabbit = rabbit(&key.load()?, iv.load()?.as_ref());
+            rabbit.skip(offset);
+            with_output(&output, |output| {
+                let mut buf = vec![0; KEYSTREAM_CHUNK];
+                let mut left = length;
+                while left > 0 {
+                    let chunk = &mut buf[..left.min(KEYSTREAM_CHUNK as u64) as usize];
+                    chunk.fill(0);
+                    rabbit.encrypt_inplace(chunk);
+                    if hex {
+                        output.write_all(hex::encode(&chunk).as_bytes())?;
+                    } else {
+                        output.write_all(chunk)?;
+                    }
+                    left -= chunk.len() as u64;
+                }
+                if hex {
+                    writeln!(output)?;
+                }
+                Ok(())
+            })
+        }
+    }
+}
+
+fn encrypt(args: &Crypt, output: &mut dyn Write) -> Result<()> {
+    let key = args.key.load()?;
+    let iv = args.iv.load()?;
+    if !args.auth && iv.is_none() && !args.no_iv {
+        return Err("raw encryption needs an IV (--iv, --iv-file, --iv-env) or --no-iv".into());
+    }
+    let mut input = open_input(&args.input)?;
+    if !args.auth {
+        io::copy(&mut input, &mut Stream::new(rabbit(&key, iv.as_ref()), output))?;
+        return Ok(());
+    }
+    let iv = match iv {
+        Some(iv) => iv,
+        None => {
+            let mut bytes = [0; 8];
+            getrandom::fill(&mut bytes).map_err(|err| format!("random: {}", err))?;
+            InitVec::from(bytes)
+        }
+    };
+    if args.chunk_size == 0 || args.chunk_size > container::MAX_CHUNK_SIZE {
+        return Err(format!("chunk size must be in 1..={}", container::MAX_CHUNK_SIZE).into());
+    }
+    let key_id = parse_key_id(&args.key_id)?.unwrap_or([0; 8]);
+    let header = Header::new(key_id, iv).authenticated(args.chunk_size);
+    let mut encryptor = FileEncryptor::new(header, &key, output)?;
+    io::copy(&mut input, &mut encryptor)?;
+    encryptor.finish()?;
+    Ok(())
+}
+
+fn decrypt(args: &Crypt, output: &mut dyn Write) -> Result<()> {
+    let key = args.key.load()?;
+    let iv = args.iv.load()?;
+    let mut input = open_input(&args.input)?;
+    if !args.auth {
+        io::copy(&mut Stream::new(rabbit(&key, iv.as_ref()), input), output)?;
+        return Ok(());
+    }
+    if iv.is_some() {
+        return Err("IV of authenticated container is read from its header".into());
+    }
+    let header = Header::read_from(&mut input)?;
+    if let Some(key_id) = parse_key_id(&args.key_id)? {
+        if *header.key_id() != key_id {
+            return Err(format!("container key id is {}", hex::encode(header.key_id())).into());
+        }
+    }
+    let mut decryptor = FileDecryptor::with_header(header, &key, input);
+    io::copy(&mut decryptor, output).map_err(container::Error::from)?;
+    Ok(())
+}
+
+fn rabbit(key: &Key, iv: Option<&InitVec>) -> Rabbit {
+    match iv {
+        Some(iv) => Rabbit::new_iv(key, iv),
+        None => Rabbit::new(key),
+    }
+}
+
+impl KeyArgs {
+    fn load(&self) -> Result<Key> {
+        let bytes = load_secret(&self.key, &self.key_file, &self.key_env, 16, "key")?;
+        Ok(Key::from(&bytes[..]))
+    }
+}
+
+impl IvArgs {
+    fn load(&self) -> Result<Option<InitVec>> {
+        if self.iv.is_none() && self.iv_file.is_none() && self.iv_env.is_none() {
+            return Ok(None);
+        }
+        let bytes = load_secret(&self.iv, &self.iv_file, &self.iv_env, 8, "IV")?;
+        Ok(Some(InitVec::from(&bytes[..])))
+    }
+}
+
+/// Loads `len` bytes given as hex, as a file with raw bytes or hex, or as an environment
+/// variable with hex.
+fn load_secret(
+    hex: &Option<String>,
+    file: &Option<PathBuf>,
+    var: &Option<String>,
+    len: usize,
+    what: &str,
+) -> Result<Vec<u8>> {
+    let text = if let Some(ref hex) = *hex {
+        hex.clone()
+    } else if let Some(ref path) = *file {
+        let bytes = fs::read(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        if bytes.len() == len {
+            return Ok(bytes);
+        }
+        String::from_utf8(bytes).map_err(|_| format!("{}: expected {} raw bytes or hex", what, len))?
+    } else if let Some(ref var) = *var {
+        env::var(var).map_err(|err| format!("{}: {}", var, err))?
+    } else {
+        return Err(format!("{} is required", what).into());
+    };
+    parse_hex(&text, len, what)
+}
+
+fn parse_hex(text: &str, len: usize, what: &str) -> Result<Vec<u8>> {
+    let bytes = hex::decode(text.trim()).map_err(|err| format!("{}: {}", what, err))?;
+    if bytes.len() != len {
+        return Err(format!("{}: expected {} hex digits", what, len * 2).into());
+    }
+    Ok(bytes)
+}
+
+fn parse_key_id(key_id: &Option<String>) -> Result<Option<[u8; 8]>> {
+    match *key_id {
+        Some(ref text) => {
+            let mut key_id = [0; 8];
+            key_id.copy_from_slice(&parse_hex(text, 8, "key id")?);
+            Ok(Some(key_id))
+        }
+        None => Ok(None),
+    }
+}
+
+fn is_std(path: &Option<PathBuf>) -> bool {
+    path.as_ref().is_none_or(|path| path == Path::new("-"))
+}
+
+fn open_input(path: &Option<PathBuf>) -> Result<Box<dyn Read>> {
+    match *path {
+        _ if is_std(path) => Ok(Box::new(io::stdin().lock())),
+        Some(ref path) => {
+            let file = File::open(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+            Ok(Box::new(BufReader::new(file)))
+        }
+        None => unreachable!(),
+    }
+}
+
+/// Runs `f` over buffered output. Output file is removed if `f` fails.
+fn with_output<F>(path: &Option<PathBuf>, f: F) -> Result<()>
+where
+    F: FnOnce(&mut dyn Write) -> Result<()>,
+{
+    if is_std(path) {
+        let stdout = io::stdout();
+        let mut output = BufWriter::new(stdout.lock());
+        f(&mut output)?;
+        output.flush()?;
+        return Ok(());
+    }
+    let path = path.as_ref().unwrap();
+    let file = File::create(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    let mut output = BufWriter::new(file);
+    let result = f(&mut output).and_then(|()| output.flush().map_err(Into::into));
+    if result.is_err() {
+        drop(output);
+        let _ = fs::remove_file(path);
+    }
+    result
+}
@@ -0,0 +1,220 @@
+use std::fs;
+
+use assert_cmd::Command;
+use rabbit::{InitVec, Key, Rabbit};
+use tempfile::tempdir;
+
+const KEY: &str = "000102030405060708090a0b0c0d0e0f";
+const IV: &str = "1011121314151617";
+
+fn rabbit() -> Command {
+    Command::cargo_bin("rabbit").unwrap()
+}
+
+fn plaintext() -> Vec<u8> {
+    (0..100_000u32).map(|i| (i * 7) as u8).collect()
+}
+
+#[test]
+fn keygen() {
+    let first = rabbit().arg("keygen").assert().success().get_output().stdout.clone();
+    let second = rabbit().arg("keygen").assert().success().get_output().stdout.clone();
+    assert_eq!(first.len(), 33);
+    assert_ne!(first, second);
+    let iv = rabbit().args(["keygen", "--iv"]).assert().success().get_output().stdout.clone();
+    assert_eq!(iv.len(), 17);
+}
+
+#[test]
+fn keystream_matches_rfc4503_vector() {
+    // RFC 4503, Appendix A.1, first key; bytes are written in order of generation.
+    rabbit()
+        .args(["keystream", "--key", "00000000000000000000000000000000", "--length", "32", "--hex"])
+        .assert()
+        .success()
+        .stdout(concat!(
+            "02f74a1c26456bf5ecd6a536f05457b1",
+            "a78ac689476c697b390c9cc515d8e888\n"
+        ));
+    rabbit()
+        .args(["keystream", "--key", "00000000000000000000000000000000"])
+        .args(["--offset", "16", "--length", "16", "--hex"])
+        .assert()
+        .success()
+        .stdout("a78ac689476c697b390c9cc515d8e888\n");
+}
+
+#[test]
+fn keystream_with_large_offset_and_length() {
+    let mut expected = vec![0; 200_000];
+    Rabbit::new_iv(&Key::from(&hex(KEY)[..]), &InitVec::from(&hex(IV)[..]))
+        .encrypt_inplace(&mut expected);
+    rabbit()
+        .args(["keystream", "--key", KEY, "--iv", IV, "--offset", "65541", "--length", "100000"])
+        .assert()
+        .success()
+        .stdout(expected[65541..165541].to_vec());
+    let mut hex_expected = hex::encode(&expected[65541..165541]);
+    hex_expected.push('\n');
+    rabbit()
+        .args(["keystream", "--key", KEY, "--iv", IV, "--offset", "65541", "--length", "100000"])
+        .arg("--hex")
+        .assert()
+        .success()
+        .stdout(hex_expected);
+}
+
+#[test]
+fn raw_matches_library() {
+    let mut expected = plaintext();
+    Rabbit::new_iv(&Key::from(&hex(KEY)[..]), &InitVec::from(&hex(IV)[..]))
+        .encrypt_inplace(&mut expected);
+    rabbit()
+        .args(["encrypt", "--key", KEY, "--iv", IV])
+        .write_stdin(plaintext())
+        .assert()
+        .success()
+        .stdout(expected);
+}
+
+#[test]
+fn raw_without_iv() {
+    rabbit()
+        .args(["encrypt", "--key", KEY])
+        .write_stdin(plaintext())
+        .assert()
+        .failure()
+        .stderr("rabbit: raw encryption needs an IV (--iv, --iv-file, --iv-env) or --no-iv\n");
+    rabbit().args(["encrypt", "--key", KEY, "--iv", IV, "--no-iv"]).assert().failure();
+
+    let mut expected = plaintext();
+    Rabbit::new(&Key::from(&hex(KEY)[..])).encrypt_inplace(&mut expected);
+    rabbit()
+        .args(["encrypt", "--no-iv", "--key", KEY])
+        .write_stdin(plaintext())
+        .assert()
+        .success()
+        .stdout(expected.clone());
+    rabbit()
+        .args(["decrypt", "--key", KEY])
+        .write_stdin(expected)
+        .assert()
+        .success()
+        .stdout(plaintext());
+}
+
+#[test]
+fn raw_roundtrip_with_files_and_env() {
+    let dir = tempdir().unwrap();
+    let key_file = dir.path().join("key");
+    let input = dir.path().join("plain");
+    let encrypted = dir.path().join("encrypted");
+    let decrypted = dir.path().join("decrypted");
+    fs::write(&key_file, hex(KEY)).unwrap();
+    fs::write(&input, plaintext()).unwrap();
+
+    rabbit()
+        .args(["encrypt", "--key-file"])
+        .arg(&key_file)
+        .args(["--iv-env", "RABBIT_TEST_IV", "-i"])
+        .arg(&input)
+        .arg("-o")
+        .arg(&encrypted)
+        .env("RABBIT_TEST_IV", IV)
+        .assert()
+        .success();
+    assert_ne!(fs::read(&encrypted).unwrap(), plaintext());
+
+    rabbit()
+        .args(["decrypt", "--key-env", "RABBIT_TEST_KEY", "--iv", IV, "-i"])
+        .arg(&encrypted)
+        .arg("-o")
+        .arg(&decrypted)
+        .env("RABBIT_TEST_KEY", KEY)
+        .assert()
+        .success();
+    assert_eq!(fs::read(&decrypted).unwrap(), plaintext());
+}
+
+#[test]
+fn authenticated_roundtrip_over_pipes() {
+    let encrypted = rabbit()
+        .args(["encrypt", "--auth", "--key", KEY, "--key-id", "0102030405060708"])
+        .args(["--chunk-size", "1000"])
+        .write_stdin(plaintext())
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    assert_eq!(&encrypted[..4], b"RBBT");
+    assert_eq!(&encrypted[12..20], &[1, 2, 3, 4, 5, 6, 7, 8]);
+
+    rabbit()
+        .args(["decrypt", "--auth", "--key", KEY, "--key-id", "0102030405060708"])
+        .write_stdin(encrypted.clone())
+        .assert()
+        .success()
+        .stdout(plaintext());
+
+    rabbit()
+        .args(["decrypt", "--auth", "--key", KEY, "--key-id", "0000000000000000"])
+        .write_stdin(encrypted)
+        .assert()
+        .failure()
+        .stderr("rabbit: container key id is 0102030405060708\n");
+}
+
+#[test]
+fn authenticated_corruption_removes_output() {
+    let dir = tempdir().unwrap();
+    let encrypted = dir.path().join("encrypted");
+    let decrypted = dir.path().join("decrypted");
+    rabbit()
+        .args(["encrypt", "--auth", "--key", KEY, "-o"])
+        .arg(&encrypted)
+        .write_stdin(plaintext())
+        .assert()
+        .success();
+
+    let mut data = fs::read(&encrypted).unwrap();
+    let last = data.len() - 1;
+    data[last] ^= 1;
+    fs::write(&encrypted, data).unwrap();
+
+    rabbit()
+        .args(["decrypt", "--auth", "--key", KEY, "-i"])
+        .arg(&encrypted)
+        .arg("-o")
+        .arg(&decrypted)
+        .assert()
+        .failure()
+        .stderr("rabbit: chunk 1 failed authentication\n");
+    assert!(!decrypted.exists());
+}
+
+#[test]
+fn invalid_arguments() {
+    rabbit().args(["encrypt"]).assert().failure();
+    rabbit()
+        .args(["encrypt", "--key", "0011"])
+        .assert()
+        .failure()
+        .stderr("rabbit: key: expected 32 hex digits\n");
+    rabbit()
+        .args(["encrypt", "--key", KEY, "--key-env", "X"])
+        .assert()
+        .failure();
+    rabbit()
+        .args(["decrypt", "--auth", "--key", KEY, "--iv", IV])
+        .assert()
+        .failure()
+        .stderr("rabbit: IV of authenticated container is read from its header\n");
+}
+
+fn hex(text: &str) -> Vec<u8> {
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap())
+        .collect()
+}
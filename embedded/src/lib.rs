@@ -0,0 +1,312 @@
+//! `embedded-io` and `embedded-io-async` adapters for `Rabbit`.
+//!
+//! `Reader` decrypts data read from a wrapped reader in place, `Writer` encrypts data
+//! through a fixed scratch buffer of `N` bytes before writing it. Neither allocates, so
+//! they work on `no_std` targets; enable `nostd` feature there, it builds `rabbit`
+//! without std:
+//!
+//! ```toml
+//! rabbit = "0.1"
+//! rabbit-embedded-io = { version = "0.1", features = ["nostd"] }
+//! ```
+//!
+//! Like `rabbit::Stream`, an adapter is out of sync with its peer after the wrapped
+//! reader or writer fails.
+//!
+//! ```ignore
+//! let mut uart = Writer::<_, 64>::new(Rabbit::new_iv(&key, &iv), uart);
+//! uart.write_all(b"telemetry")?;
+//! ```
+#![no_std]
+
+use embedded_io::{ErrorType, Read, Write};
+use rabbit::Rabbit;
+
+/// Default scratch buffer size of `Writer`.
+pub const DEFAULT_SCRATCH_LEN: usize = 32;
+
+/// Decrypts everything read from `R`.
+pub struct Reader<R> {
+    inner: R,
+    rabbit: Rabbit,
+}
+
+impl<R> Reader<R> {
+    pub fn new(rabbit: Rabbit, inner: R) -> Reader<R> {
+        Reader { inner, rabbit }
+    }
+
+    pub fn into_inner(self) -> (Rabbit, R) {
+        (self.rabbit, self.inner)
+    }
+}
+
+impl<R: ErrorType> ErrorType for Reader<R> {
+    type Error = R::Error;
+}
+
+impl<R: Read> Read for Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, R::Error> {
+        let count = self.inner.read(buf)?;
+        self.rabbit.decrypt_inplace(&mut buf[..count]);
+        Ok(count)
+    }
+}
+
+impl<R: embedded_io_async::Read> embedded_io_async::Read for Reader<R> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, R::Error> {
+        let count = self.inner.read(buf).await?;
+        self.rabbit.decrypt_inplace(&mut buf[..count]);
+        Ok(count)
+    }
+}
+
+/// Encrypts everything written to `W`, up to `N` bytes at a time.
+pub struct Writer<W, const N: usize = DEFAULT_SCRATCH_LEN> {
+    inner: W,
+    rabbit: Rabbit,
+    scratch: [u8; N],
+}
+
+impl<W, const N: usize> Writer<W, N> {
+    pub fn new(rabbit: Rabbit, inner: W) -> Writer<W, N> {
+        Writer {
+            inner,
+            rabbit,
+            scratch: [0; N],
+        }
+    }
+
+    pub fn into_inner(self) -> (Rabbit, W) {
+        (self.rabbit, self.inner)
+    }
+
+    /// Encrypts start of `buf` into scratch buffer, returns its length.
+    fn encrypt(&mut self, buf: &[u8]) -> usize {
+        let len = buf.len().min(N);
+        self.rabbit.encrypt(&buf[..len], &mut self.scratch[..len]);
+        len
+    }
+}
+
+impl<W: ErrorType, const N: usize> ErrorType for Writer<W, N> {
+    type Error = W::Error;
+}
+
+impl<W: Write, const N: usize> Write for Writer<W, N> {
+    /// Writes up to `N` bytes of `buf`, all of them unless the wrapped writer fails.
+    fn write(&mut self, buf: &[u8]) -> Result<usize, W::Error> {
+        let len = self.encrypt(buf);
+        self.inner.write_all(&self.scratch[..len])?;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> Result<(), W::Error> {
+        self.inner.flush()
+    }
+}
+
+impl<W: embedded_io_async::Write, const N: usize> embedded_io_async::Write for Writer<W, N> {
+    /// Writes up to `N` bytes of `buf`, all of them unless the wrapped writer fails.
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, W::Error> {
+        let len = self.encrypt(buf);
+        self.inner.write_all(&self.scratch[..len]).await?;
+        Ok(len)
+    }
+
+    async fn flush(&mut self) -> Result<(), W::Error> {
+        self.inner.flush().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use core::convert::Infallible;
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+    use std::vec::Vec;
+
+    use embedded_io::{ErrorKind, ErrorType, Read, Write};
+    use rabbit::{InitVec, Key, Rabbit};
+
+    use super::{Reader, Writer};
+
+    /// Serial port which moves at most `chunk` bytes per call.
+    struct Serial {
+        rx: Vec<u8>,
+        tx: Vec<u8>,
+        chunk: usize,
+        fail_after: Option<usize>,
+    }
+
+    impl Serial {
+        fn new(rx: &[u8], chunk: usize) -> Serial {
+            Serial {
+                rx: rx.to_vec(),
+                tx: Vec::new(),
+                chunk,
+                fail_after: None,
+            }
+        }
+
+        fn check(&self) -> Result<(), ErrorKind> {
+            match self.fail_after {
+                Some(limit) if self.tx.len() >= limit => Err(ErrorKind::TimedOut),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    impl ErrorType for Serial {
+        type Error = ErrorKind;
+    }
+
+    impl Read for Serial {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+            let count = buf.len().min(self.chunk).min(self.rx.len());
+            buf[..count].copy_from_slice(&self.rx[..count]);
+            self.rx.drain(..count);
+            Ok(count)
+        }
+    }
+
+    impl Write for Serial {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+            self.check()?;
+            let count = buf.len().min(self.chunk);
+            self.tx.extend_from_slice(&buf[..count]);
+            Ok(count)
+        }
+
+        fn flush(&mut self) -> Result<(), ErrorKind> {
+            self.check()
+        }
+    }
+
+    impl embedded_io_async::Read for Serial {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+            Read::read(self, buf)
+        }
+    }
+
+    impl embedded_io_async::Write for Serial {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+            Write::write(self, buf)
+        }
+
+        async fn flush(&mut self) -> Result<(), ErrorKind> {
+            Write::flush(self)
+        }
+    }
+
+    /// Polls a future which never waits, as futures of `Serial` are.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        match future.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("future is pending"),
+        }
+    }
+
+    fn rabbit() -> Rabbit {
+        Rabbit::new_iv(&Key::from([0x5a; 16]), &InitVec::from(0x0123_4567_89ab_cdef))
+    }
+
+    fn data() -> Vec<u8> {
+        (0..1000).map(|i| (i * 7) as u8).collect()
+    }
+
+    fn encrypted(data: &[u8]) -> Vec<u8> {
+        let mut output = data.to_vec();
+        rabbit().encrypt_inplace(&mut output);
+        output
+    }
+
+    #[test]
+    fn writer_encrypts_with_short_writes() {
+        let data = data();
+        for &chunk in [1, 3, 32, 100, 4096].iter() {
+            let mut writer = Writer::<_, 16>::new(rabbit(), Serial::new(&[], chunk));
+            for part in data.chunks(27) {
+                writer.write_all(part).unwrap();
+            }
+            writer.flush().unwrap();
+            assert_eq!(writer.into_inner().1.tx, encrypted(&data), "chunk {}", chunk);
+        }
+    }
+
+    #[test]
+    fn writer_accepts_at_most_scratch_len() {
+        let mut writer = Writer::<_, 8>::new(rabbit(), Serial::new(&[], 3));
+        assert_eq!(writer.write(&[0; 20]).unwrap(), 8);
+        assert_eq!(writer.write(&[0; 5]).unwrap(), 5);
+        assert_eq!(writer.into_inner().1.tx, encrypted(&[0; 13]));
+    }
+
+    #[test]
+    fn writer_reports_serial_errors() {
+        let mut serial = Serial::new(&[], 4);
+        serial.fail_after = Some(10);
+        let mut writer: Writer<_> = Writer::new(rabbit(), serial);
+        assert_eq!(writer.write_all(&data()), Err(ErrorKind::TimedOut));
+        assert_eq!(writer.flush(), Err(ErrorKind::TimedOut));
+    }
+
+    #[test]
+    fn reader_decrypts_with_short_reads() {
+        let data = data();
+        for &chunk in [1, 5, 64, 4096].iter() {
+            let mut reader = Reader::new(rabbit(), Serial::new(&encrypted(&data), chunk));
+            let mut output = Vec::new();
+            let mut buf = [0; 50];
+            loop {
+                match reader.read(&mut buf).unwrap() {
+                    0 => break,
+                    count => output.extend_from_slice(&buf[..count]),
+                }
+            }
+            assert_eq!(output, data, "chunk {}", chunk);
+        }
+    }
+
+    #[test]
+    fn async_round_trip() {
+        let data = data();
+        let mut writer = Writer::<_, 24>::new(rabbit(), Serial::new(&[], 7));
+        block_on(async {
+            for part in data.chunks(100) {
+                embedded_io_async::Write::write_all(&mut writer, part).await?;
+            }
+            embedded_io_async::Write::flush(&mut writer).await
+        })
+        .unwrap();
+        let tx = writer.into_inner().1.tx;
+        assert_eq!(tx, encrypted(&data));
+
+        let mut reader = Reader::new(rabbit(), Serial::new(&tx, 9));
+        let mut output = std::vec![0; data.len()];
+        block_on(embedded_io_async::Read::read_exact(&mut reader, &mut output)).unwrap();
+        assert_eq!(output, data);
+    }
+
+    #[test]
+    fn error_type_is_passed_through() {
+        struct Sink;
+        impl ErrorType for Sink {
+            type Error = Infallible;
+        }
+        impl Write for Sink {
+            fn write(&mut self, buf: &[u8]) -> Result<usize, Infallible> {
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> Result<(), Infallible> {
+                Ok(())
+            }
+        }
+        let mut writer: Writer<_> = Writer::new(rabbit(), Sink);
+        let Ok(()) = writer.write_all(b"infallible");
+    }
+}
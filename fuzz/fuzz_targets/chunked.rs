@@ -0,0 +1,130 @@
+//! `Rabbit` driven through random operations and chunk boundaries, compared against
+//! one-shot encryption of the whole message. Checked operations get buffers of any
+//! length and must report `LengthMismatch` instead of panicking.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use rabbit::{Error, InitVec, Key, Rabbit};
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    Encrypt { len: u16, slack: u8 },
+    Decrypt { len: u16, slack: u8 },
+    EncryptInplace(u16),
+    DecryptInplace(u16),
+    Skip(u16),
+    TryEncrypt { len: u16, buf_len: u16 },
+    TryDecrypt { len: u16, buf_len: u16 },
+    EncryptB2b { len: u16, buf_len: u16 },
+    DecryptB2b { len: u16, buf_len: u16 },
+}
+
+impl Op {
+    /// Length of data and whether the operation succeeds.
+    fn len(&self) -> (usize, bool) {
+        match *self {
+            Op::Encrypt { len, .. } | Op::Decrypt { len, .. } => (len as usize, true),
+            Op::EncryptInplace(len) | Op::DecryptInplace(len) | Op::Skip(len) => {
+                (len as usize, true)
+            }
+            Op::TryEncrypt { len, buf_len } | Op::TryDecrypt { len, buf_len } => {
+                (len as usize, buf_len >= len)
+            }
+            Op::EncryptB2b { len, buf_len } | Op::DecryptB2b { len, buf_len } => {
+                (len as usize, buf_len == len)
+            }
+        }
+    }
+
+    /// Keystream bytes the operation consumes.
+    fn consumed(&self) -> usize {
+        match self.len() {
+            (len, true) => len,
+            (_, false) => 0,
+        }
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    key: [u8; 16],
+    iv: Option<u64>,
+    ops: Vec<Op>,
+    data: Vec<u8>,
+}
+
+fn rabbit(input: &Input) -> Rabbit {
+    match input.iv {
+        Some(iv) => Rabbit::new_iv(&Key::from(input.key), &InitVec::from(iv)),
+        None => Rabbit::new(&Key::from(input.key)),
+    }
+}
+
+fuzz_target!(|input: Input| {
+    let total: usize = input.ops.iter().map(|op| op.consumed()).sum();
+    let mut keystream = vec![0; total];
+    rabbit(&input).encrypt_inplace(&mut keystream);
+
+    let mut rabbit = rabbit(&input);
+    let mut pos = 0;
+    for op in input.ops.iter() {
+        let (len, ok) = op.len();
+        let consumed = op.consumed();
+        let data: Vec<u8> = (0..len)
+            .map(|i| input.data.get(i % input.data.len().max(1)).cloned().unwrap_or(0))
+            .collect();
+        let expected: Vec<u8> = data
+            .iter()
+            .zip(&keystream[pos..pos + consumed])
+            .map(|(d, k)| d ^ k)
+            .collect();
+        match *op {
+            Op::Encrypt { slack, .. } | Op::Decrypt { slack, .. } => {
+                // Bytes of `buf` past `data.len()` must stay untouched.
+                let mut buf = vec![0xA5; len + slack as usize];
+                if let Op::Encrypt { .. } = *op {
+                    rabbit.encrypt(&data, &mut buf);
+                } else {
+                    rabbit.decrypt(&data, &mut buf);
+                }
+                assert_eq!(&buf[..len], &expected[..]);
+                assert!(buf[len..].iter().all(|&b| b == 0xA5));
+            }
+            Op::EncryptInplace(_) | Op::DecryptInplace(_) => {
+                let mut buf = data;
+                if let Op::EncryptInplace(_) = *op {
+                    rabbit.encrypt_inplace(&mut buf);
+                } else {
+                    rabbit.decrypt_inplace(&mut buf);
+                }
+                assert_eq!(buf, expected);
+            }
+            Op::Skip(_) => rabbit.skip(len as u64),
+            Op::TryEncrypt { buf_len, .. }
+            | Op::TryDecrypt { buf_len, .. }
+            | Op::EncryptB2b { buf_len, .. }
+            | Op::DecryptB2b { buf_len, .. } => {
+                let mut buf = vec![0xA5; buf_len as usize];
+                let result = match *op {
+                    Op::TryEncrypt { .. } => rabbit.try_encrypt(&data, &mut buf),
+                    Op::TryDecrypt { .. } => rabbit.try_decrypt(&data, &mut buf),
+                    Op::EncryptB2b { .. } => rabbit.encrypt_b2b(&data, &mut buf),
+                    _ => rabbit.decrypt_b2b(&data, &mut buf),
+                };
+                if ok {
+                    assert_eq!(result, Ok(()));
+                    assert_eq!(&buf[..len], &expected[..]);
+                    assert!(buf[len..].iter().all(|&b| b == 0xA5));
+                } else {
+                    // Rejected call leaves `buf` and keystream position untouched.
+                    assert_eq!(result, Err(Error::LengthMismatch { data: len, buf: buf.len() }));
+                    assert!(buf.iter().all(|&b| b == 0xA5));
+                }
+            }
+        }
+        pos += consumed;
+        assert_eq!(rabbit.block_counter(), (pos as u64).div_ceil(16));
+    }
+});
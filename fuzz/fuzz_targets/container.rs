@@ -0,0 +1,59 @@
+//! Container header parsing and decryption of arbitrary files, plus round trip of
+//! arbitrary data. After a failed read, later reads must not return data.
+
+#![no_main]
+
+use std::convert::TryInto;
+use std::io::{Read, Write};
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use rabbit::container::{FileDecryptor, FileEncryptor, Header, HEADER_LEN};
+use rabbit::{InitVec, Key};
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    key: [u8; 16],
+    file: Vec<u8>,
+    chunk_size: Option<u16>,
+    write_size: u16,
+    read_size: u16,
+}
+
+fuzz_target!(|input: Input| {
+    let key = Key::from(input.key);
+
+    // Arbitrary bytes: parsing and decryption may fail but must not panic, and accepted
+    // headers serialize back to the same bytes.
+    if input.file.len() >= HEADER_LEN {
+        let bytes: &[u8; HEADER_LEN] = input.file[..HEADER_LEN].try_into().unwrap();
+        if let Ok(header) = Header::from_bytes(bytes) {
+            assert_eq!(&header.to_bytes(), bytes);
+        }
+    }
+    if let Ok(mut decryptor) = FileDecryptor::new(&key, &input.file[..]) {
+        let mut buf = vec![0; input.read_size as usize + 1];
+        let mut failures = 0;
+        while failures < 3 {
+            match decryptor.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => assert_eq!(failures, 0, "{} bytes read after an error", n),
+                Err(_) => failures += 1,
+            }
+        }
+    }
+
+    // Round trip of `file` as plaintext.
+    let mut header = Header::new(*b"fuzzkey!", InitVec::from(input.file.len() as u64));
+    if let Some(chunk_size) = input.chunk_size {
+        header = header.authenticated(chunk_size as u32 + 1);
+    }
+    let mut encryptor = FileEncryptor::new(header, &key, Vec::new()).unwrap();
+    for piece in input.file.chunks(input.write_size as usize + 1) {
+        encryptor.write_all(piece).unwrap();
+    }
+    let encrypted = encryptor.finish().unwrap();
+    let mut decrypted = Vec::new();
+    FileDecryptor::new(&key, &encrypted[..]).unwrap().read_to_end(&mut decrypted).unwrap();
+    assert_eq!(decrypted, input.file);
+});
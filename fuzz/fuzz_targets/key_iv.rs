@@ -0,0 +1,62 @@
+//! `Key` and `InitVec` construction from arbitrary slices.
+//!
+//! The fuzzer chooses slice lengths. `From<&[u8]>` must panic exactly when the length is
+//! wrong (`TryFrom<&[u8]>` comes from it through the blanket impl and panics the same).
+
+#![no_main]
+
+use std::panic::{self, UnwindSafe};
+
+use libfuzzer_sys::fuzz_target;
+use rabbit::{InitVec, Key, Rabbit};
+
+/// Runs `f`, `None` if it panics. Panic hook of libfuzzer aborts, so it is replaced
+/// meanwhile.
+fn catch<T>(f: impl FnOnce() -> T + UnwindSafe) -> Option<T> {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let result = panic::catch_unwind(f);
+    panic::set_hook(hook);
+    result.ok()
+}
+
+fuzz_target!(|data: &[u8]| {
+    let (lengths, rest) = data.split_at(data.len().min(2));
+    let key_len = lengths.first().map_or(16, |&len| len as usize % 33);
+    let iv_len = lengths.get(1).map_or(8, |&len| len as usize % 17);
+    let (key_bytes, rest) = rest.split_at(rest.len().min(key_len));
+    let (iv_bytes, _) = rest.split_at(rest.len().min(iv_len));
+
+    let key = catch(|| Key::from(key_bytes));
+    assert_eq!(key.is_some(), key_bytes.len() == 16, "key of {} bytes", key_bytes.len());
+    let iv = catch(|| InitVec::from(iv_bytes));
+    assert_eq!(iv.is_some(), iv_bytes.len() == 8, "IV of {} bytes", iv_bytes.len());
+
+    let key = match key {
+        Some(key) => key,
+        None => return,
+    };
+    assert_eq!(&key[..], key_bytes);
+    let mut array = [0; 16];
+    array.copy_from_slice(key_bytes);
+    assert_eq!(Key::from(array), key);
+
+    let mut plain = [0; 64];
+    Rabbit::new(&key).encrypt_inplace(&mut plain);
+
+    if let Some(iv) = iv {
+        assert_eq!(&iv[..], iv_bytes);
+        let mut array = [0; 8];
+        array.copy_from_slice(iv_bytes);
+        assert_eq!(InitVec::from(array), iv);
+        assert_eq!(InitVec::from(u64::from_le_bytes(array)), iv);
+
+        let mut with_iv = [0; 64];
+        Rabbit::new_iv(&key, &iv).encrypt_inplace(&mut with_iv);
+        let mut reinit = [0; 64];
+        let mut rabbit = Rabbit::new(&key);
+        rabbit.reinit(&iv);
+        rabbit.encrypt_inplace(&mut reinit);
+        assert_eq!(with_iv, reinit);
+    }
+});
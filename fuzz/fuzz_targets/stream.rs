@@ -0,0 +1,121 @@
+//! `Stream` over inner reader and writer that inject short transfers, `Interrupted` and
+//! hard errors. Everything that got through must match the keystream of a one-shot run.
+
+#![no_main]
+
+use std::io::{self, Read, Write};
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use rabbit::{InitVec, Key, Rabbit, Stream};
+
+#[derive(Arbitrary, Debug, Clone, Copy)]
+enum Fault {
+    /// Transfer at most this many bytes (at least one).
+    Short(u8),
+    Interrupted,
+    WouldBlock,
+    Fail,
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    key: [u8; 16],
+    iv: Option<u64>,
+    data: Vec<u8>,
+    faults: Vec<Fault>,
+    writes: Vec<u8>,
+}
+
+struct Faulty<'a, T> {
+    inner: T,
+    faults: &'a [Fault],
+    next: usize,
+}
+
+impl<'a, T> Faulty<'a, T> {
+    /// Limit for this call or error. Once faults run out everything is transferred, so
+    /// `write_all` retrying `Interrupted` terminates.
+    fn fault(&mut self, len: usize) -> io::Result<usize> {
+        let fault = match self.faults.get(self.next) {
+            Some(&fault) => fault,
+            None => return Ok(len),
+        };
+        self.next += 1;
+        match fault {
+            Fault::Short(max) => Ok(len.min(max as usize + 1)),
+            Fault::Interrupted => Err(io::ErrorKind::Interrupted.into()),
+            Fault::WouldBlock => Err(io::ErrorKind::WouldBlock.into()),
+            Fault::Fail => Err(io::Error::other("injected")),
+        }
+    }
+}
+
+impl<'a> Read for Faulty<'a, &'a [u8]> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.fault(buf.len())?;
+        self.inner.read(&mut buf[..len])
+    }
+}
+
+impl<'a> Write for Faulty<'a, Vec<u8>> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.fault(buf.len())?;
+        self.inner.write(&buf[..len])
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn rabbit(input: &Input) -> Rabbit {
+    match input.iv {
+        Some(iv) => Rabbit::new_iv(&Key::from(input.key), &InitVec::from(iv)),
+        None => Rabbit::new(&Key::from(input.key)),
+    }
+}
+
+fuzz_target!(|input: Input| {
+    let mut expected = input.data.clone();
+    rabbit(&input).encrypt_inplace(&mut expected);
+
+    // Reading: failed reads must not consume keystream.
+    let inner = Faulty { inner: &input.data[..], faults: &input.faults, next: 0 };
+    let mut reader = Stream::new(rabbit(&input), inner);
+    let mut out = Vec::new();
+    let mut buf = [0; 300];
+    for _ in 0..input.data.len() + input.faults.len() + 1 {
+        match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => out.extend_from_slice(&buf[..n]),
+            Err(_) => {}
+        }
+    }
+    assert_eq!(out, expected);
+
+    // Writing: succeeds as a whole or stops at first hard error with a valid prefix written.
+    let inner = Faulty { inner: Vec::new(), faults: &input.faults, next: 0 };
+    let mut writer = Stream::new(rabbit(&input), inner);
+    let mut data = &input.data[..];
+    let mut failed = false;
+    for &size in input.writes.iter().chain(std::iter::repeat(&255)) {
+        if data.is_empty() {
+            break;
+        }
+        let (piece, rest) = data.split_at(data.len().min(size as usize + 1));
+        match writer.write(piece) {
+            Ok(n) => assert_eq!(n, piece.len()),
+            Err(_) => {
+                failed = true;
+                break;
+            }
+        }
+        data = rest;
+    }
+    let written = writer.into_inner().1.inner;
+    assert_eq!(&written[..], &expected[..written.len()]);
+    if !failed {
+        assert_eq!(written, expected);
+    }
+});
@@ -0,0 +1,28 @@
+//! Generates key of `rabbit_encrypt!`, random or from `RABBIT_MACROS_KEY` (32 hex digits).
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+const KEY_VAR: &str = "RABBIT_MACROS_KEY";
+
+fn main() {
+    println!("cargo:rerun-if-env-changed={}", KEY_VAR);
+    let mut key = [0u8; 16];
+    match env::var(KEY_VAR) {
+        Ok(hex) => {
+            assert!(
+                hex.len() == 32 && hex.is_ascii(),
+                "{} must be 32 hex digits",
+                KEY_VAR
+            );
+            for (i, byte) in key.iter_mut().enumerate() {
+                *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+                    .unwrap_or_else(|_| panic!("{} must be 32 hex digits", KEY_VAR));
+            }
+        }
+        Err(_) => getrandom::fill(&mut key).expect("no random source"),
+    }
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("key.rs");
+    fs::write(out, format!("{:?}", key)).unwrap();
+}
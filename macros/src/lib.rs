@@ -0,0 +1,96 @@
+//! `rabbit_encrypt!` keeps string literals out of the binary in plain text.
+//!
+//! The literal is encrypted during compilation with a key generated when this crate is
+//! built (set `RABBIT_MACROS_KEY` to 32 hex digits for reproducible builds) and an IV
+//! derived from the key and the literal. The expansion decrypts it with
+//! `Rabbit::new_iv` on first use and evaluates to `&'static str`:
+//!
+//! ```ignore
+//! use rabbit_macros::rabbit_encrypt;
+//!
+//! let token: &'static str = rabbit_encrypt!("secret token");
+//! ```
+//!
+//! This is obfuscation only: key and IV are stored in the binary next to the
+//! ciphertext. The expansion refers to `::rabbit` and `::std`, so the calling crate
+//! needs `rabbit` as a dependency (without `nostd` feature). `rabbit` can't re-export the
+//! macro because this crate depends on it.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use rabbit::{InitVec, Key, Rabbit};
+use syn::{parse_macro_input, LitStr};
+
+const KEY: [u8; 16] = include!(concat!(env!("OUT_DIR"), "/key.rs"));
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// IV of `plain`: FNV-1a of key and `plain`. Equal literals get equal ciphertexts.
+fn iv(key: &[u8; 16], plain: &[u8]) -> [u8; 8] {
+    let mut hash = FNV_OFFSET;
+    for &byte in key.iter().chain(plain) {
+        hash = (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME);
+    }
+    hash.to_le_bytes()
+}
+
+/// Returns IV and ciphertext of `plain`.
+fn encrypt(key: &[u8; 16], plain: &[u8]) -> ([u8; 8], Vec<u8>) {
+    let iv = iv(key, plain);
+    let mut data = plain.to_vec();
+    Rabbit::new_iv(&Key::new(*key), &InitVec::new(iv)).encrypt_inplace(&mut data);
+    (iv, data)
+}
+
+/// Encrypts a string literal at compile time, evaluates to `&'static str` decrypted on
+/// first use. See crate documentation.
+#[proc_macro]
+pub fn rabbit_encrypt(input: TokenStream) -> TokenStream {
+    let literal = parse_macro_input!(input as LitStr);
+    let (iv, data) = encrypt(&KEY, literal.value().as_bytes());
+    let key = KEY;
+    let len = data.len();
+    quote! {{
+        static PLAIN: ::std::sync::OnceLock<::std::string::String> =
+            ::std::sync::OnceLock::new();
+        PLAIN
+            .get_or_init(|| {
+                const KEY: ::rabbit::Key = ::rabbit::Key::new([#(#key),*]);
+                const IV: ::rabbit::InitVec = ::rabbit::InitVec::new([#(#iv),*]);
+                static DATA: [u8; #len] = [#(#data),*];
+                // Keeps the optimizer from decrypting at compile time.
+                let mut plain = ::core::hint::black_box(&DATA).to_vec();
+                ::rabbit::Rabbit::new_iv(&KEY, &IV).decrypt_inplace(&mut plain);
+                ::std::string::String::from_utf8(plain).expect("decrypted literal is UTF-8")
+            })
+            .as_str()
+    }}
+    .into()
+}
+
+#[cfg(test)]
+mod test {
+    use rabbit::{InitVec, Key, Rabbit};
+
+    use super::{encrypt, iv};
+
+    #[test]
+    fn encrypts_with_rabbit() {
+        let key = [0x42; 16];
+        let (iv, data) = encrypt(&key, b"hello, world");
+        assert_ne!(&data[..], b"hello, world");
+        let mut plain = data;
+        Rabbit::new_iv(&Key::from(key), &InitVec::from(iv)).decrypt_inplace(&mut plain);
+        assert_eq!(&plain[..], b"hello, world");
+    }
+
+    #[test]
+    fn iv_depends_on_key_and_literal() {
+        let key = [0x42; 16];
+        assert_eq!(iv(&key, b"a"), iv(&key, b"a"));
+        assert_ne!(iv(&key, b"a"), iv(&key, b"b"));
+        assert_ne!(iv(&key, b"a"), iv(&[0x43; 16], b"a"));
+        assert_ne!(iv(&key, b""), iv(&key, b"\0"));
+    }
+}
@@ -0,0 +1,32 @@
+use rabbit_macros::rabbit_encrypt;
+
+/// Finds `needle` in the running test binary.
+fn in_binary(needle: &[u8]) -> bool {
+    let binary = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+    binary.windows(needle.len()).any(|window| window == needle)
+}
+
+#[test]
+fn decrypts_literals() {
+    assert_eq!(rabbit_encrypt!("hello, world"), "hello, world");
+    assert_eq!(rabbit_encrypt!(""), "");
+    assert_eq!(rabbit_encrypt!("päßwörd ✓"), "päßwörd ✓");
+    assert_eq!(rabbit_encrypt!(r"raw \n string"), "raw \\n string");
+    assert_eq!(rabbit_encrypt!("escaped \n\t\"string\""), "escaped \n\t\"string\"");
+}
+
+#[test]
+fn decrypts_on_first_use_only() {
+    let first = (0..3).map(|_| rabbit_encrypt!("cached").as_ptr()).collect::<Vec<_>>();
+    assert!(first.iter().all(|&ptr| ptr == first[0]));
+}
+
+#[test]
+fn literal_is_not_in_binary() {
+    let secret = rabbit_encrypt!("obfuscated literal 0x5eC12e7");
+    // Built at runtime, so the literal appears only in `rabbit_encrypt!`.
+    let expected = "7e21Ce5x0 laretil detacsufbo".chars().rev().collect::<String>();
+    assert_eq!(secret, expected);
+    assert!(!in_binary(expected.as_bytes()));
+    assert!(in_binary(b"7e21Ce5x0 laretil detacsufbo"));
+}
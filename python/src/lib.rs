@@ -0,0 +1,157 @@
+//! Python module `rabbit`.
+//!
+//! ```python
+//! import rabbit
+//!
+//! cipher = rabbit.Rabbit(key, iv)
+//! ciphertext = cipher.encrypt(b"data")
+//! cipher.reset()
+//! buf = bytearray(ciphertext)
+//! cipher.decrypt_inplace(buf)
+//! ```
+
+use pyo3::buffer::PyBuffer;
+use pyo3::exceptions::{PyBufferError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use rabbit::{InitVec, Key, Mode};
+
+/// Rabbit stream cipher. `Rabbit(key)` uses key setup only, `Rabbit(key, iv)` key and
+/// IV setup; `key` is 16 bytes and `iv` is 8 bytes.
+#[pyclass(module = "rabbit")]
+struct Rabbit {
+    inner: rabbit::Rabbit,
+}
+
+fn key(key: &[u8]) -> PyResult<Key> {
+    if key.len() != 16 {
+        return Err(PyValueError::new_err(format!(
+            "key must be 16 bytes, got {}",
+            key.len()
+        )));
+    }
+    Ok(Key::from(key))
+}
+
+fn iv(iv: &[u8]) -> PyResult<InitVec> {
+    if iv.len() != 8 {
+        return Err(PyValueError::new_err(format!(
+            "iv must be 8 bytes, got {}",
+            iv.len()
+        )));
+    }
+    Ok(InitVec::from(iv))
+}
+
+impl Rabbit {
+    /// Encrypts any bytes-like object into new `bytes`, without the GIL.
+    fn apply<'py>(
+        &mut self,
+        py: Python<'py>,
+        data: &Bound<'py, PyAny>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let mut data = PyBuffer::<u8>::get(data)?.to_vec(py)?;
+        let inner = &mut self.inner;
+        py.detach(|| inner.encrypt_inplace(&mut data));
+        Ok(PyBytes::new(py, &data))
+    }
+
+    /// Encrypts writable C-contiguous buffer (`bytearray`, `memoryview`, ...) in place.
+    fn apply_inplace(&mut self, buf: &Bound<'_, PyAny>) -> PyResult<()> {
+        let buffer = PyBuffer::<u8>::get(buf)?;
+        if buffer.readonly() {
+            return Err(PyTypeError::new_err("buffer must be writable"));
+        }
+        if !buffer.is_c_contiguous() {
+            return Err(PyBufferError::new_err("buffer must be C-contiguous"));
+        }
+        let len = buffer.len_bytes();
+        if len > 0 {
+            // Writable contiguous buffer of `len` bytes, kept alive and exported (so not
+            // resized) by `buffer` while we hold the GIL.
+            let data = unsafe { std::slice::from_raw_parts_mut(buffer.buf_ptr() as *mut u8, len) };
+            self.inner.encrypt_inplace(data);
+        }
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl Rabbit {
+    #[new]
+    #[pyo3(signature = (key, iv = None))]
+    fn new(key: &[u8], iv: Option<&[u8]>) -> PyResult<Rabbit> {
+        let key = self::key(key)?;
+        let inner = match iv {
+            Some(iv) => rabbit::Rabbit::new_iv(&key, &self::iv(iv)?),
+            None => rabbit::Rabbit::new(&key),
+        };
+        Ok(Rabbit { inner })
+    }
+
+    /// Returns encrypted copy of bytes-like `data`.
+    fn encrypt<'py>(
+        &mut self,
+        py: Python<'py>,
+        data: &Bound<'py, PyAny>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        self.apply(py, data)
+    }
+
+    /// Returns decrypted copy of bytes-like `data`.
+    fn decrypt<'py>(
+        &mut self,
+        py: Python<'py>,
+        data: &Bound<'py, PyAny>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        self.apply(py, data)
+    }
+
+    /// Encrypts writable buffer (`bytearray`, writable `memoryview`, ...) in place.
+    fn encrypt_inplace(&mut self, buf: &Bound<'_, PyAny>) -> PyResult<()> {
+        self.apply_inplace(buf)
+    }
+
+    /// Decrypts writable buffer in place.
+    fn decrypt_inplace(&mut self, buf: &Bound<'_, PyAny>) -> PyResult<()> {
+        self.apply_inplace(buf)
+    }
+
+    /// Returns next `length` bytes of keystream.
+    fn keystream<'py>(&mut self, py: Python<'py>, length: usize) -> Bound<'py, PyBytes> {
+        let mut data = vec![0; length];
+        let inner = &mut self.inner;
+        py.detach(|| inner.encrypt_inplace(&mut data));
+        PyBytes::new(py, &data)
+    }
+
+    /// Discards next `count` bytes of keystream.
+    fn skip(&mut self, count: u64) {
+        self.inner.skip(count)
+    }
+
+    /// Restarts keystream of current mode (of the constructor or of the last `reinit`).
+    fn reset(&mut self) {
+        self.inner.reset()
+    }
+
+    /// Restarts keystream from key setup followed by setup of 8-byte `iv`.
+    fn reinit(&mut self, iv: &[u8]) -> PyResult<()> {
+        self.inner.reinit(&self::iv(iv)?);
+        Ok(())
+    }
+
+    /// `True` if keystream starts from IV setup.
+    #[getter]
+    fn has_iv(&self) -> bool {
+        self.inner.mode() == Mode::WithIv
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "rabbit")]
+fn rabbit_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Rabbit>()?;
+    Ok(())
+}
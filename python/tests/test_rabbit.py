@@ -0,0 +1,139 @@
+"""Tests of the `rabbit` module, run with pytest after `maturin develop`."""
+
+import pytest
+
+import rabbit
+
+# RFC 4503 Appendix A, key and IV as hex bytes, keystream in generation order.
+VECTORS = [
+    (
+        "00000000000000000000000000000000",
+        None,
+        "02F74A1C26456BF5ECD6A536F05457B1"
+        "A78AC689476C697B390C9CC515D8E888"
+        "96D6731688D168DA51D40C70C3A116F4",
+    ),
+    (
+        "ACC351DCF162FC3BFE363D2E29132891",
+        None,
+        "9C51E28784C37FE9A127F63EC8F32D3D"
+        "19FC5485AA53BF96885B40F461CD76F5"
+        "5E4C4D20203BE58A5043DBFB737454E5",
+    ),
+    (
+        "43009BC001ABE9E933C7E08715749583",
+        None,
+        "9B60D002FD5CEB32ACCD41A0CD0DB10C"
+        "AD3EFF4C1192707B5A01170FCA9FFC95"
+        "2874943AAD4741923F7FFC8BDEE54996",
+    ),
+    (
+        "00000000000000000000000000000000",
+        "0000000000000000",
+        "EDB70567375DCD7CD89554F85E27A7C6"
+        "8D4ADC7032298F7BD4EFF504ACA6295F"
+        "668FBF478ADB2BE51E6CDE292B82DE2A",
+    ),
+    (
+        "00000000000000000000000000000000",
+        "597E26C175F573C3",
+        "6D7D012292CCDCE0E2120058B94ECD1F"
+        "2E6F93EDFF99247B012521D1104E5FA7"
+        "A79B0212D0BD56233938E793C312C1EB",
+    ),
+    (
+        "00000000000000000000000000000000",
+        "2717F4D21A56EBA6",
+        "4D1051A123AFB670BF8D8505C8D85A44"
+        "035BC3ACC667AEAE5B2CF44779F2C896"
+        "CB5115F034F03D31171CA75F89FCCB9F",
+    ),
+]
+
+
+def cipher(key, iv):
+    if iv is None:
+        return rabbit.Rabbit(bytes.fromhex(key))
+    return rabbit.Rabbit(bytes.fromhex(key), bytes.fromhex(iv))
+
+
+@pytest.mark.parametrize("key,iv,stream", VECTORS)
+def test_keystream(key, iv, stream):
+    assert cipher(key, iv).keystream(48) == bytes.fromhex(stream)
+
+
+@pytest.mark.parametrize("key,iv,stream", VECTORS)
+def test_encrypt_in_pieces(key, iv, stream):
+    r = cipher(key, iv)
+    pieces = [r.encrypt(bytes(n)) for n in (1, 15, 17, 0, 15)]
+    assert b"".join(pieces) == bytes.fromhex(stream)
+
+
+@pytest.mark.parametrize("key,iv,stream", VECTORS)
+def test_decrypt(key, iv, stream):
+    r = cipher(key, iv)
+    assert r.decrypt(bytes.fromhex(stream)) == bytes(48)
+
+
+@pytest.mark.parametrize("key,iv,stream", VECTORS)
+def test_inplace(key, iv, stream):
+    r = cipher(key, iv)
+    buf = bytearray(48)
+    r.encrypt_inplace(buf)
+    assert buf == bytes.fromhex(stream)
+
+    r.reset()
+    view = memoryview(buf)
+    r.decrypt_inplace(view[:20])
+    r.decrypt_inplace(view[20:])
+    assert buf == bytes(48)
+
+
+@pytest.mark.parametrize("key,iv,stream", VECTORS)
+def test_reset_and_skip(key, iv, stream):
+    r = cipher(key, iv)
+    r.keystream(7)
+    r.reset()
+    r.skip(5)
+    assert r.keystream(43) == bytes.fromhex(stream)[5:]
+
+
+def test_reinit():
+    key, _, stream = VECTORS[5]
+    r = cipher(key, None)
+    assert not r.has_iv
+    r.reinit(bytes.fromhex("2717F4D21A56EBA6"))
+    assert r.has_iv
+    assert r.keystream(48) == bytes.fromhex(stream)
+    r.reset()
+    assert r.keystream(48) == bytes.fromhex(stream)
+
+
+def test_accepts_bytes_like():
+    key, iv, stream = VECTORS[3]
+    expected = bytes.fromhex(stream)
+    assert cipher(key, iv).encrypt(bytearray(48)) == expected
+    assert cipher(key, iv).encrypt(memoryview(bytes(48))) == expected
+
+
+def test_invalid_lengths():
+    with pytest.raises(ValueError):
+        rabbit.Rabbit(bytes(15))
+    with pytest.raises(ValueError):
+        rabbit.Rabbit(bytes(16), bytes(9))
+    with pytest.raises(ValueError):
+        rabbit.Rabbit(bytes(16)).reinit(bytes(7))
+
+
+def test_inplace_rejects_readonly_buffer():
+    r = rabbit.Rabbit(bytes(16))
+    with pytest.raises(TypeError):
+        r.encrypt_inplace(bytes(16))
+    with pytest.raises(TypeError):
+        r.encrypt_inplace(memoryview(bytes(16)))
+
+
+def test_inplace_rejects_non_contiguous_buffer():
+    r = rabbit.Rabbit(bytes(16))
+    with pytest.raises(BufferError):
+        r.encrypt_inplace(memoryview(bytearray(16))[::2])
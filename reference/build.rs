@@ -0,0 +1,11 @@
+fn main() {
+    if std::env::var_os("CARGO_FEATURE_C").is_none() {
+        return;
+    }
+    println!("cargo:rerun-if-changed=c/rabbit.c");
+    println!("cargo:rerun-if-changed=c/rabbit.h");
+    cc::Build::new()
+        .file("c/rabbit.c")
+        .warnings(true)
+        .compile("rabbit_reference");
+}
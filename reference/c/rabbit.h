@@ -0,0 +1,40 @@
+/*
+ * Rabbit stream cipher, C implementation in the layout of the eSTREAM reference
+ * code (ECRYPT API, `rabbit.c`), used for differential testing only.
+ */
+
+#ifndef RABBIT_REFERENCE_H
+#define RABBIT_REFERENCE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+typedef uint8_t u8;
+typedef uint32_t u32;
+
+typedef struct {
+    u32 x[8];
+    u32 c[8];
+    u32 carry;
+} RABBIT_instance;
+
+typedef struct {
+    RABBIT_instance master_ctx;
+    RABBIT_instance work_ctx;
+} ECRYPT_ctx;
+
+/* Key setup, `key` is 16 bytes. Work context is a copy of master context. */
+void ECRYPT_keysetup(ECRYPT_ctx *ctx, const u8 *key);
+
+/* IV setup from master context, `iv` is 8 bytes. */
+void ECRYPT_ivsetup(ECRYPT_ctx *ctx, const u8 *iv);
+
+/* XORs `msglen` bytes of keystream into `input`. Only the last call of a message
+ * may have `msglen` that isn't a multiple of 16. */
+void ECRYPT_process_bytes(ECRYPT_ctx *ctx, const u8 *input, u8 *output, size_t msglen);
+
+/* Exposed building blocks. */
+void RABBIT_next_state(RABBIT_instance *p_instance);
+void RABBIT_extract(const RABBIT_instance *p_instance, u8 *output);
+
+#endif
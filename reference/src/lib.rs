@@ -0,0 +1,35 @@
+//! Bindings to the C implementation in `c/`, used by differential tests of `rabbit`
+//! (`src/reference.rs`). Dev-only, not published. The C code is built and bound only
+//! with `c` feature (`reference-c` feature of `rabbit`).
+//!
+//! The C code follows the layout of the eSTREAM reference code (ECRYPT API) but is not
+//! that code: it was written for these tests, so it is independent only to the extent
+//! its author didn't share mistakes with the Rust core.
+
+#![no_std]
+
+/// `RABBIT_instance`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct Instance {
+    pub x: [u32; 8],
+    pub c: [u32; 8],
+    pub carry: u32,
+}
+
+/// `ECRYPT_ctx`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct Ctx {
+    pub master_ctx: Instance,
+    pub work_ctx: Instance,
+}
+
+#[cfg(feature = "c")]
+extern "C" {
+    pub fn ECRYPT_keysetup(ctx: *mut Ctx, key: *const u8);
+    pub fn ECRYPT_ivsetup(ctx: *mut Ctx, iv: *const u8);
+    pub fn ECRYPT_process_bytes(ctx: *mut Ctx, input: *const u8, output: *mut u8, msglen: usize);
+    pub fn RABBIT_next_state(instance: *mut Instance);
+    pub fn RABBIT_extract(instance: *const Instance, output: *mut u8);
+}
@@ -0,0 +1,533 @@
+//! Encrypted and authenticated channel over a `Read + Write` transport.
+//!
+//! Handshake: each side sends `HANDSHAKE_MAGIC` and an ephemeral X25519 public key, then
+//! both compute the shared secret. HKDF-SHA256 (salt is the optional pre-shared key)
+//! expands it into a Rabbit key, base IV and HMAC-SHA256 key per direction, with info
+//! `rabbit channel v1`, sender and receiver public keys. Sides have no roles, so the same
+//! call is used on both ends.
+//!
+//! Without pre-shared key the handshake is unauthenticated, an active attacker can sit in
+//! the middle. Use `Channel::handshake_psk` with a secret known to both peers to exclude
+//! that (a wrong key on either side makes every frame fail authentication).
+//!
+//! Every message is a frame (integers are little-endian):
+//!
+//! | Size     | Field                                                          |
+//! |----------|----------------------------------------------------------------|
+//! | 4        | Ciphertext length                                              |
+//! | 8        | Sequence number, from zero in each direction                   |
+//! | length   | Message XORed with keystream of `Rabbit::new_iv(key, iv ^ seq)` |
+//! | 16       | HMAC-SHA256, truncated, of sequence number, length, ciphertext  |
+//!
+//! Receiver checks the tag, then the sequence number: a frame with a number it already
+//! accepted is reported as replayed, one with a number ahead of the next expected as
+//! reordered (frames were dropped or swapped). A replayed frame is consumed and skipped,
+//! the channel stays usable. Any other error of `recv`, and an I/O error of `send`,
+//! leaves the stream or the sequence out of sync: the channel is poisoned, and later
+//! calls return `Error::Poisoned`. Drop it and open a new one.
+//!
+//! ```ignore
+//! let stream = TcpStream::connect(addr)?;
+//! let mut channel = Channel::handshake_psk(stream, &psk)?;
+//! channel.send(b"ping")?;
+//! let reply = channel.recv()?;
+//! ```
+
+use std::error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rand_core::OsRng;
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+use super::{InitVec, Key, RabbitKey};
+
+/// First bytes of a handshake message, followed by 32-byte public key.
+pub const HANDSHAKE_MAGIC: [u8; 4] = *b"RBC\x01";
+/// Length of frame header (ciphertext length and sequence number).
+pub const HEADER_LEN: usize = 12;
+/// Length of frame authentication tag.
+pub const TAG_LEN: usize = 16;
+/// Default limit of message length.
+pub const DEFAULT_MAX_FRAME_LEN: u32 = 16 * 1024 * 1024;
+
+const INFO: &[u8] = b"rabbit channel v1";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Channel errors.
+#[derive(Debug)]
+pub enum Error {
+    /// Underlying transport failed. Poisons the channel.
+    Io(io::Error),
+    /// Peer isn't speaking this protocol.
+    BadHandshake,
+    /// Peer's public key gives an all-zero shared secret.
+    NonContributory,
+    /// Frame is longer than the limit. Poisons the channel when received.
+    FrameTooLarge(u64),
+    /// Frame failed authentication. Poisons the channel.
+    BadTag,
+    /// Frame with given sequence number was already received, it is skipped.
+    Replayed(u64),
+    /// Frame with given sequence number came before the expected one. Poisons the
+    /// channel.
+    Reordered { expected: u64, received: u64 },
+    /// Every sequence number was used, open a new channel.
+    SequenceExhausted,
+    /// Channel failed earlier and can't be used further.
+    Poisoned,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref err) => write!(f, "I/O error: {}", err),
+            Error::BadHandshake => write!(f, "peer sent invalid handshake"),
+            Error::NonContributory => write!(f, "peer sent low order public key"),
+            Error::FrameTooLarge(len) => write!(f, "frame of {} bytes is too large", len),
+            Error::BadTag => write!(f, "frame failed authentication"),
+            Error::Replayed(seq) => write!(f, "frame {} was replayed", seq),
+            Error::Reordered { expected, received } => {
+                write!(f, "expected frame {}, received frame {}", expected, received)
+            }
+            Error::SequenceExhausted => write!(f, "sequence numbers are exhausted"),
+            Error::Poisoned => write!(f, "channel failed earlier"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+/// Keys and next sequence number of one direction.
+struct Direction {
+    key: RabbitKey,
+    iv: u64,
+    mac_key: [u8; 32],
+    seq: u64,
+}
+
+impl Direction {
+    fn derive(hkdf: &Hkdf<Sha256>, sender: &PublicKey, receiver: &PublicKey) -> Direction {
+        let mut okm = [0u8; 16 + 8 + 32];
+        hkdf.expand_multi_info(&[INFO, sender.as_bytes(), receiver.as_bytes()], &mut okm)
+            .expect("output length is valid for HKDF-SHA256");
+        let mut iv = [0; 8];
+        iv.copy_from_slice(&okm[16..24]);
+        let mut mac_key = [0; 32];
+        mac_key.copy_from_slice(&okm[24..]);
+        Direction {
+            key: RabbitKey::new(&Key::from(&okm[..16])),
+            iv: u64::from_le_bytes(iv),
+            mac_key,
+            seq: 0,
+        }
+    }
+
+    fn apply(&self, seq: u64, data: &mut [u8]) {
+        let iv = InitVec::from(self.iv ^ seq);
+        self.key.rabbit_iv(&iv).encrypt_inplace(data);
+    }
+
+    fn mac(&self, header: &[u8; HEADER_LEN], ciphertext: &[u8]) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.mac_key).expect("HMAC takes any key length");
+        mac.update(&header[4..]);
+        mac.update(&header[..4]);
+        mac.update(ciphertext);
+        mac
+    }
+}
+
+/// Duplex channel, see module documentation.
+pub struct Channel<S> {
+    stream: S,
+    send: Direction,
+    recv: Direction,
+    max_frame_len: u32,
+    poisoned: bool,
+}
+
+impl<S: Read + Write> Channel<S> {
+    /// Performs unauthenticated handshake over `stream`.
+    pub fn handshake(stream: S) -> Result<Channel<S>, Error> {
+        Channel::handshake_with(stream, None)
+    }
+
+    /// Performs handshake over `stream`, authenticated by pre-shared key `psk`.
+    pub fn handshake_psk(stream: S, psk: &[u8]) -> Result<Channel<S>, Error> {
+        Channel::handshake_with(stream, Some(psk))
+    }
+
+    fn handshake_with(mut stream: S, psk: Option<&[u8]>) -> Result<Channel<S>, Error> {
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let public = PublicKey::from(&secret);
+        let mut hello = [0u8; 36];
+        hello[..4].copy_from_slice(&HANDSHAKE_MAGIC);
+        hello[4..].copy_from_slice(public.as_bytes());
+        stream.write_all(&hello)?;
+        stream.flush()?;
+
+        stream.read_exact(&mut hello)?;
+        if hello[..4] != HANDSHAKE_MAGIC {
+            return Err(Error::BadHandshake);
+        }
+        let mut peer = [0u8; 32];
+        peer.copy_from_slice(&hello[4..]);
+        let peer = PublicKey::from(peer);
+
+        let shared = secret.diffie_hellman(&peer);
+        if !shared.was_contributory() {
+            return Err(Error::NonContributory);
+        }
+        let hkdf = Hkdf::<Sha256>::new(psk, shared.as_bytes());
+        Ok(Channel {
+            stream,
+            send: Direction::derive(&hkdf, &public, &peer),
+            recv: Direction::derive(&hkdf, &peer, &public),
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+            poisoned: false,
+        })
+    }
+
+    /// Sets limit of message length for both directions, `DEFAULT_MAX_FRAME_LEN` by
+    /// default. Peers should use the same limit.
+    pub fn max_frame_len(mut self, len: u32) -> Channel<S> {
+        self.max_frame_len = len;
+        self
+    }
+
+    /// Encrypts and sends `message` as one frame.
+    pub fn send(&mut self, message: &[u8]) -> Result<(), Error> {
+        if self.poisoned {
+            return Err(Error::Poisoned);
+        }
+        if message.len() as u64 > u64::from(self.max_frame_len) {
+            return Err(Error::FrameTooLarge(message.len() as u64));
+        }
+        let seq = self.send.seq;
+        if seq == u64::MAX {
+            return Err(Error::SequenceExhausted);
+        }
+        let mut header = [0u8; HEADER_LEN];
+        header[..4].copy_from_slice(&(message.len() as u32).to_le_bytes());
+        header[4..].copy_from_slice(&seq.to_le_bytes());
+
+        let mut frame = Vec::with_capacity(HEADER_LEN + message.len() + TAG_LEN);
+        frame.extend_from_slice(&header);
+        frame.extend_from_slice(message);
+        self.send.apply(seq, &mut frame[HEADER_LEN..]);
+        let tag = self.send.mac(&header, &frame[HEADER_LEN..]).finalize().into_bytes();
+        frame.extend_from_slice(&tag[..TAG_LEN]);
+
+        self.send.seq += 1;
+        let result = self.stream.write_all(&frame).and_then(|_| self.stream.flush());
+        self.poisoned = result.is_err();
+        result.map_err(Error::Io)
+    }
+
+    /// Receives and decrypts next frame.
+    pub fn recv(&mut self) -> Result<Vec<u8>, Error> {
+        if self.poisoned {
+            return Err(Error::Poisoned);
+        }
+        let result = self.recv_frame();
+        match result {
+            Ok(_) | Err(Error::Replayed(_)) => {}
+            Err(_) => self.poisoned = true,
+        }
+        result
+    }
+
+    fn recv_frame(&mut self) -> Result<Vec<u8>, Error> {
+        let mut header = [0u8; HEADER_LEN];
+        self.stream.read_exact(&mut header)?;
+        let mut len = [0; 4];
+        len.copy_from_slice(&header[..4]);
+        let len = u32::from_le_bytes(len);
+        if len > self.max_frame_len {
+            return Err(Error::FrameTooLarge(u64::from(len)));
+        }
+        let mut seq = [0; 8];
+        seq.copy_from_slice(&header[4..]);
+        let seq = u64::from_le_bytes(seq);
+
+        let mut data = vec![0; len as usize + TAG_LEN];
+        self.stream.read_exact(&mut data)?;
+        let tag = data.split_off(len as usize);
+        if self.recv.mac(&header, &data).verify_truncated_left(&tag).is_err() {
+            return Err(Error::BadTag);
+        }
+        if seq < self.recv.seq {
+            return Err(Error::Replayed(seq));
+        }
+        if seq > self.recv.seq {
+            return Err(Error::Reordered {
+                expected: self.recv.seq,
+                received: seq,
+            });
+        }
+        self.recv.apply(seq, &mut data);
+        self.recv.seq += 1;
+        Ok(data)
+    }
+}
+
+impl<S> Channel<S> {
+    /// Returns underlying transport.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::VecDeque;
+    use std::io::{self, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::{Arc, Condvar, Mutex};
+    use std::thread;
+
+    use super::{Channel, Error, HANDSHAKE_MAGIC, HEADER_LEN, TAG_LEN};
+
+    /// Blocking in-memory byte queue.
+    #[derive(Clone, Default)]
+    struct Queue(Arc<(Mutex<VecDeque<u8>>, Condvar)>);
+
+    impl Queue {
+        fn push(&self, bytes: &[u8]) {
+            let (ref data, ref ready) = *self.0;
+            data.lock().unwrap().extend(bytes);
+            ready.notify_all();
+        }
+
+        /// Takes everything queued so far.
+        fn take(&self) -> Vec<u8> {
+            self.0 .0.lock().unwrap().drain(..).collect()
+        }
+    }
+
+    /// One end of an in-memory duplex pipe.
+    struct Pipe {
+        incoming: Queue,
+        outgoing: Queue,
+    }
+
+    impl Read for Pipe {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let (ref data, ref ready) = *self.incoming.0;
+            let mut data = data.lock().unwrap();
+            while data.is_empty() {
+                data = ready.wait(data).unwrap();
+            }
+            let n = buf.len().min(data.len());
+            for (dst, src) in buf.iter_mut().zip(data.drain(..n)) {
+                *dst = src;
+            }
+            Ok(n)
+        }
+    }
+
+    impl Write for Pipe {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.outgoing.push(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Channels over in-memory pipes and the queues from `a` to `b` and back.
+    fn pair(psk_a: &'static [u8], psk_b: &'static [u8]) -> (Channel<Pipe>, Channel<Pipe>, Queue, Queue) {
+        let (a_to_b, b_to_a) = (Queue::default(), Queue::default());
+        let a = Pipe {
+            incoming: b_to_a.clone(),
+            outgoing: a_to_b.clone(),
+        };
+        let b = Pipe {
+            incoming: a_to_b.clone(),
+            outgoing: b_to_a.clone(),
+        };
+        let b = thread::spawn(move || Channel::handshake_psk(b, psk_b).unwrap());
+        let a = Channel::handshake_psk(a, psk_a).unwrap();
+        (a, b.join().unwrap(), a_to_b, b_to_a)
+    }
+
+    /// Sends `message` from `a` and returns the frame without delivering it.
+    fn intercept(a: &mut Channel<Pipe>, queue: &Queue, message: &[u8]) -> Vec<u8> {
+        a.send(message).unwrap();
+        queue.take()
+    }
+
+    #[test]
+    fn duplex_over_pipes() {
+        let (mut a, mut b, _, _) = pair(b"psk", b"psk");
+        for i in 0..10 {
+            let message = vec![i as u8; i * 100];
+            a.send(&message).unwrap();
+            b.send(&message[..i]).unwrap();
+            assert_eq!(b.recv().unwrap(), message);
+            assert_eq!(a.recv().unwrap(), &message[..i]);
+        }
+    }
+
+    #[test]
+    fn duplex_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut channel = Channel::handshake(stream).unwrap();
+            while let Ok(message) = channel.recv() {
+                let reply: Vec<u8> = message.iter().rev().cloned().collect();
+                channel.send(&reply).unwrap();
+            }
+        });
+
+        let mut channel = Channel::handshake(TcpStream::connect(addr).unwrap()).unwrap();
+        for len in [0, 1, 100, 70000].iter() {
+            let message: Vec<u8> = (0..*len).map(|i| i as u8).collect();
+            channel.send(&message).unwrap();
+            let reply = channel.recv().unwrap();
+            assert_eq!(reply, message.iter().rev().cloned().collect::<Vec<u8>>());
+        }
+        drop(channel);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn ciphertext_differs_from_message_and_between_frames() {
+        let (mut a, _, a_to_b, _) = pair(b"", b"");
+        let first = intercept(&mut a, &a_to_b, &[0; 64]);
+        let second = intercept(&mut a, &a_to_b, &[0; 64]);
+        assert_eq!(first.len(), HEADER_LEN + 64 + TAG_LEN);
+        assert_ne!(&first[HEADER_LEN..HEADER_LEN + 64], &[0; 64][..]);
+        assert_ne!(first[HEADER_LEN..], second[HEADER_LEN..]);
+    }
+
+    #[test]
+    fn detects_replay() {
+        let (mut a, mut b, a_to_b, _) = pair(b"psk", b"psk");
+        let frame = intercept(&mut a, &a_to_b, b"transfer 100");
+        a_to_b.push(&frame);
+        a_to_b.push(&frame);
+        assert_eq!(b.recv().unwrap(), b"transfer 100");
+        match b.recv() {
+            Err(Error::Replayed(0)) => {}
+            other => panic!("{:?}", other),
+        }
+        a.send(b"next").unwrap();
+        assert_eq!(b.recv().unwrap(), b"next");
+    }
+
+    #[test]
+    fn detects_reordering() {
+        let (mut a, mut b, a_to_b, _) = pair(b"psk", b"psk");
+        let first = intercept(&mut a, &a_to_b, b"first");
+        let second = intercept(&mut a, &a_to_b, b"second");
+        a_to_b.push(&second);
+        a_to_b.push(&first);
+        match b.recv() {
+            Err(Error::Reordered {
+                expected: 0,
+                received: 1,
+            }) => {}
+            other => panic!("{:?}", other),
+        }
+        // The rest of the stream can't be trusted, even a frame that would be accepted.
+        match b.recv() {
+            Err(Error::Poisoned) => {}
+            other => panic!("{:?}", other),
+        }
+        match b.send(b"reply") {
+            Err(Error::Poisoned) => {}
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn detects_tampering() {
+        // Sequence number, ciphertext and tag; a changed length would make `recv` wait
+        // for more data.
+        let frame_len = HEADER_LEN + 14 + TAG_LEN;
+        for &position in [4, 11, HEADER_LEN, HEADER_LEN + 13, frame_len - 1].iter() {
+            let (mut a, mut b, a_to_b, _) = pair(b"psk", b"psk");
+            let mut frame = intercept(&mut a, &a_to_b, b"attack at dawn");
+            frame[position] ^= 1;
+            a_to_b.push(&frame);
+            match b.recv() {
+                Err(Error::BadTag) => {}
+                other => panic!("{}: {:?}", position, other),
+            }
+            a.send(b"retry").unwrap();
+            match b.recv() {
+                Err(Error::Poisoned) => {}
+                other => panic!("{}: {:?}", position, other),
+            }
+        }
+    }
+
+    #[test]
+    fn psk_mismatch_fails_authentication() {
+        let (mut a, mut b, _, _) = pair(b"one", b"two");
+        a.send(b"hello").unwrap();
+        match b.recv() {
+            Err(Error::BadTag) => {}
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn enforces_max_frame_len() {
+        let (a, b, a_to_b, _) = pair(b"", b"");
+        let (mut a, mut b) = (a.max_frame_len(10), b.max_frame_len(10));
+        match a.send(&[0; 11]) {
+            Err(Error::FrameTooLarge(11)) => {}
+            other => panic!("{:?}", other),
+        }
+        a_to_b.push(&[0xFF, 0xFF, 0xFF, 0xFF]);
+        a_to_b.push(&[0; 8]);
+        match b.recv() {
+            Err(Error::FrameTooLarge(0xFFFF_FFFF)) => {}
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_bad_handshake() {
+        let (incoming, outgoing) = (Queue::default(), Queue::default());
+        incoming.push(b"HTTP/1.1 200 OK\r\n");
+        incoming.push(&[0; 19]);
+        let pipe = Pipe { incoming, outgoing };
+        match Channel::handshake(pipe) {
+            Err(Error::BadHandshake) => {}
+            other => panic!("{:?}", other.map(|_| ())),
+        }
+
+        let (incoming, outgoing) = (Queue::default(), Queue::default());
+        incoming.push(&HANDSHAKE_MAGIC);
+        incoming.push(&[0; 32]);
+        let pipe = Pipe { incoming, outgoing };
+        match Channel::handshake(pipe) {
+            Err(Error::NonContributory) => {}
+            other => panic!("{:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,372 @@
+//! Encrypted frame codec for `tokio_util::codec`.
+//!
+//! Every frame is (integers are little-endian):
+//!
+//! | Size     | Field                     |
+//! |----------|---------------------------|
+//! | 4        | Ciphertext length         |
+//! | length   | Ciphertext                |
+//! | 16       | Tag                       |
+//!
+//! Codec doesn't use the long-term key directly: both peers derive a session key, the
+//! first 16 bytes of HMAC-SHA256 keyed with it over `"rabbit codec v1"` and a session
+//! nonce. The nonce must be fresh for every connection (e.g. 16 random bytes sent by the
+//! initiator, or negotiated) and the same on both peers. Reusing a nonce with the same key
+//! repeats keystreams of the previous connection, so XOR of their frames reveals both
+//! plaintexts and recorded frames authenticate again.
+//!
+//! Frame `n` of a side uses keystream of `Rabbit::reinit` with IV `n`, with the top bit set
+//! for frames of `Side::Responder`, so both sides can share the session key. As in `container`,
+//! the first 32 bytes of that keystream are an HMAC-SHA256 key and the rest encrypts the
+//! frame. Tag is the HMAC-SHA256, truncated to 16 bytes, of length and ciphertext. Frame
+//! numbers aren't sent: a dropped, replayed or reordered frame fails authentication.
+//!
+//! ```ignore
+//! let codec = RabbitCodec::new(&key, Side::Initiator, &session_nonce);
+//! let mut framed = Framed::new(socket, codec);
+//! framed.send(&b"request"[..]).await?;
+//! let reply = framed.next().await.unwrap()?;
+//! ```
+
+use std::error;
+use std::fmt;
+use std::io;
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tokio_util::codec::{Decoder, Encoder};
+
+use super::{InitVec, Key, Rabbit};
+
+/// Length of frame header.
+pub const HEADER_LEN: usize = 4;
+/// Length of frame authentication tag.
+pub const TAG_LEN: usize = 16;
+/// Default limit of frame payload length.
+pub const DEFAULT_MAX_FRAME_LEN: usize = 8 * 1024 * 1024;
+/// Length of session nonce.
+pub const SESSION_NONCE_LEN: usize = 16;
+
+const SESSION_INFO: &[u8] = b"rabbit codec v1";
+const MAC_KEY_LEN: usize = 32;
+const RESPONDER: u64 = 1 << 63;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Which end of a connection a codec is on. Peers must use different sides.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Side {
+    /// Sends frames with IVs below `2^63`.
+    Initiator,
+    /// Sends frames with IVs from `2^63`.
+    Responder,
+}
+
+/// Codec errors.
+#[derive(Debug)]
+pub enum Error {
+    /// Underlying transport failed.
+    Io(io::Error),
+    /// Frame payload is longer than the limit.
+    FrameTooLarge(usize),
+    /// Frame failed authentication.
+    BadTag,
+    /// Every frame number of this side was used, change the key.
+    FramesExhausted,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref err) => write!(f, "I/O error: {}", err),
+            Error::FrameTooLarge(len) => write!(f, "frame of {} bytes is too large", len),
+            Error::BadTag => write!(f, "frame failed authentication"),
+            Error::FramesExhausted => write!(f, "frame numbers are exhausted"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+/// Encoder and decoder of encrypted frames, see module documentation.
+pub struct RabbitCodec {
+    rabbit: Rabbit,
+    side: Side,
+    sent: u64,
+    received: u64,
+    max_frame_len: usize,
+}
+
+impl RabbitCodec {
+    /// Codec of one connection, `session_nonce` must never repeat for `key`.
+    pub fn new(key: &Key, side: Side, session_nonce: &[u8; SESSION_NONCE_LEN]) -> RabbitCodec {
+        let mut mac = HmacSha256::new_from_slice(&key[..]).expect("HMAC takes any key length");
+        mac.update(SESSION_INFO);
+        mac.update(session_nonce);
+        let session_key = Key::from(&mac.finalize().into_bytes()[..16]);
+        RabbitCodec {
+            rabbit: Rabbit::new(&session_key),
+            side,
+            sent: 0,
+            received: 0,
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+        }
+    }
+
+    /// Sets limit of frame payload length for both directions, `DEFAULT_MAX_FRAME_LEN` by
+    /// default. Peers should use the same limit.
+    pub fn max_frame_len(mut self, len: usize) -> RabbitCodec {
+        self.max_frame_len = len;
+        self
+    }
+
+    /// Reinitializes cipher for frame `n` sent by `side`, returns MAC keyed with its
+    /// keystream.
+    fn start_frame(&mut self, side: Side, n: u64) -> HmacSha256 {
+        let iv = match side {
+            Side::Initiator => n,
+            Side::Responder => RESPONDER | n,
+        };
+        self.rabbit.reinit(&InitVec::from(iv));
+        let mut mac_key = [0; MAC_KEY_LEN];
+        self.rabbit.encrypt_inplace(&mut mac_key);
+        HmacSha256::new_from_slice(&mac_key).expect("HMAC takes any key length")
+    }
+}
+
+impl<'a> Encoder<&'a [u8]> for RabbitCodec {
+    type Error = Error;
+
+    fn encode(&mut self, item: &'a [u8], dst: &mut BytesMut) -> Result<(), Error> {
+        if item.len() > self.max_frame_len || item.len() > u32::MAX as usize {
+            return Err(Error::FrameTooLarge(item.len()));
+        }
+        if self.sent == RESPONDER {
+            return Err(Error::FramesExhausted);
+        }
+        let (side, sent) = (self.side, self.sent);
+        let mut mac = self.start_frame(side, sent);
+
+        dst.reserve(HEADER_LEN + item.len() + TAG_LEN);
+        let header = (item.len() as u32).to_le_bytes();
+        dst.put_slice(&header);
+        let start = dst.len();
+        dst.put_slice(item);
+        self.rabbit.encrypt_inplace(&mut dst[start..]);
+        mac.update(&header);
+        mac.update(&dst[start..]);
+        dst.put_slice(&mac.finalize().into_bytes()[..TAG_LEN]);
+        self.sent += 1;
+        Ok(())
+    }
+}
+
+impl Encoder<Bytes> for RabbitCodec {
+    type Error = Error;
+
+    fn encode(&mut self, item: Bytes, dst: &mut BytesMut) -> Result<(), Error> {
+        self.encode(&item[..], dst)
+    }
+}
+
+impl Decoder for RabbitCodec {
+    type Item = BytesMut;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<BytesMut>, Error> {
+        if src.len() < HEADER_LEN {
+            return Ok(None);
+        }
+        let mut header = [0; HEADER_LEN];
+        header.copy_from_slice(&src[..HEADER_LEN]);
+        let len = u32::from_le_bytes(header) as usize;
+        if len > self.max_frame_len {
+            return Err(Error::FrameTooLarge(len));
+        }
+        if src.len() < HEADER_LEN + len + TAG_LEN {
+            src.reserve(HEADER_LEN + len + TAG_LEN - src.len());
+            return Ok(None);
+        }
+        if self.received == RESPONDER {
+            return Err(Error::FramesExhausted);
+        }
+
+        src.advance(HEADER_LEN);
+        let mut data = src.split_to(len);
+        let tag = src.split_to(TAG_LEN);
+        let peer = match self.side {
+            Side::Initiator => Side::Responder,
+            Side::Responder => Side::Initiator,
+        };
+        let received = self.received;
+        let mut mac = self.start_frame(peer, received);
+        mac.update(&header);
+        mac.update(&data);
+        if mac.verify_truncated_left(&tag).is_err() {
+            return Err(Error::BadTag);
+        }
+        self.rabbit.decrypt_inplace(&mut data);
+        self.received += 1;
+        Ok(Some(data))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate futures_util;
+    extern crate tokio;
+
+    use bytes::BytesMut;
+    use tokio_util::codec::{Decoder, Encoder, Framed};
+
+    use self::futures_util::{SinkExt, StreamExt};
+    use super::{Error, RabbitCodec, Side, HEADER_LEN, SESSION_NONCE_LEN, TAG_LEN};
+    use super::super::Key;
+
+    fn session(nonce: &[u8; SESSION_NONCE_LEN]) -> (RabbitCodec, RabbitCodec) {
+        let key = Key::from([0x42; 16]);
+        (RabbitCodec::new(&key, Side::Initiator, nonce),
+         RabbitCodec::new(&key, Side::Responder, nonce))
+    }
+
+    fn codecs() -> (RabbitCodec, RabbitCodec) {
+        session(&[7; SESSION_NONCE_LEN])
+    }
+
+    fn encode(codec: &mut RabbitCodec, message: &[u8]) -> BytesMut {
+        let mut frame = BytesMut::new();
+        codec.encode(message, &mut frame).unwrap();
+        frame
+    }
+
+    #[test]
+    fn frames_over_duplex() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let (a, b) = tokio::io::duplex(1024 * 1024);
+        let (initiator, responder) = codecs();
+        let mut a = Framed::new(a, initiator);
+        let mut b = Framed::new(b, responder);
+
+        let messages: Vec<Vec<u8>> = (0..20).map(|i| vec![i as u8; i * 37]).collect();
+        for message in messages.iter() {
+            runtime.block_on(a.send(&message[..])).unwrap();
+        }
+        for message in messages.iter() {
+            let received = runtime.block_on(b.next()).unwrap().unwrap();
+            assert_eq!(&received[..], &message[..]);
+            runtime.block_on(b.send(&received[..])).unwrap();
+        }
+        for message in messages.iter() {
+            let echoed = runtime.block_on(a.next()).unwrap().unwrap();
+            assert_eq!(&echoed[..], &message[..]);
+        }
+    }
+
+    #[test]
+    fn oversized_frame_over_duplex() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let (a, b) = tokio::io::duplex(1024);
+        let (initiator, responder) = codecs();
+        let mut a = Framed::new(a, initiator.max_frame_len(200));
+        let mut b = Framed::new(b, responder.max_frame_len(100));
+
+        match runtime.block_on(a.send(&[0; 201][..])) {
+            Err(Error::FrameTooLarge(201)) => {}
+            other => panic!("{:?}", other),
+        }
+        runtime.block_on(a.send(&[0; 150][..])).unwrap();
+        match runtime.block_on(b.next()) {
+            Some(Err(Error::FrameTooLarge(150))) => {}
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn waits_for_whole_frame() {
+        let (mut initiator, mut responder) = codecs();
+        let frame = encode(&mut initiator, b"partial delivery");
+        assert_eq!(frame.len(), HEADER_LEN + 16 + TAG_LEN);
+        let mut src = BytesMut::new();
+        for &byte in frame[..frame.len() - 1].iter() {
+            src.extend_from_slice(&[byte]);
+            assert!(responder.decode(&mut src).unwrap().is_none());
+        }
+        src.extend_from_slice(&frame[frame.len() - 1..]);
+        assert_eq!(&responder.decode(&mut src).unwrap().unwrap()[..], b"partial delivery");
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn sides_use_different_keystreams() {
+        let (mut initiator, mut responder) = codecs();
+        let a = encode(&mut initiator, &[0; 32]);
+        let b = encode(&mut responder, &[0; 32]);
+        assert_ne!(a, b);
+        assert_ne!(&a[HEADER_LEN..HEADER_LEN + 32], &[0; 32][..]);
+    }
+
+    #[test]
+    fn sessions_use_different_keystreams() {
+        let (mut first, mut first_peer) = session(&[1; SESSION_NONCE_LEN]);
+        let (mut second, mut second_peer) = session(&[2; SESSION_NONCE_LEN]);
+        let a = encode(&mut first, &[0; 32]);
+        let b = encode(&mut second, &[0; 32]);
+        assert_ne!(&a[HEADER_LEN..HEADER_LEN + 32], &b[HEADER_LEN..HEADER_LEN + 32]);
+
+        // Recorded frame of one session doesn't authenticate in another.
+        let mut src = a.clone();
+        assert!(matches!(second_peer.decode(&mut src), Err(Error::BadTag)));
+        let mut src = a;
+        assert_eq!(&first_peer.decode(&mut src).unwrap().unwrap()[..], &[0; 32][..]);
+    }
+
+    #[test]
+    fn detects_tampering() {
+        for position in 0..HEADER_LEN + 5 + TAG_LEN {
+            let (mut initiator, mut responder) = codecs();
+            let mut frame = encode(&mut initiator, b"hello");
+            frame[position] ^= 0x01;
+            match responder.decode(&mut frame) {
+                Err(Error::BadTag) => {}
+                // Changed length, frame is incomplete or too large.
+                Ok(None) | Err(Error::FrameTooLarge(_)) if position < HEADER_LEN => {}
+                other => panic!("{}: {:?}", position, other),
+            }
+        }
+    }
+
+    #[test]
+    fn detects_replay_reordering_and_reflection() {
+        let (mut initiator, mut responder) = codecs();
+        let first = encode(&mut initiator, b"first");
+        let second = encode(&mut initiator, b"second");
+
+        let mut src = second.clone();
+        assert!(matches!(responder.decode(&mut src), Err(Error::BadTag)));
+        let mut src = first.clone();
+        assert_eq!(&responder.decode(&mut src).unwrap().unwrap()[..], b"first");
+        let mut src = first.clone();
+        assert!(matches!(responder.decode(&mut src), Err(Error::BadTag)));
+        let mut src = second.clone();
+        assert_eq!(&responder.decode(&mut src).unwrap().unwrap()[..], b"second");
+
+        // Frame sent back to its sender.
+        let (mut initiator, _) = codecs();
+        let mut src = encode(&mut initiator, b"echo");
+        assert!(matches!(initiator.decode(&mut src), Err(Error::BadTag)));
+    }
+}
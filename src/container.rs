@@ -0,0 +1,662 @@
+//! Encrypted file container.
+//!
+//! Every file starts with a fixed 28-byte header (integers are little-endian):
+//!
+//! | Offset | Size | Field                                                   |
+//! |--------|------|---------------------------------------------------------|
+//! | 0      | 4    | Magic bytes `RBBT`                                      |
+//! | 4      | 1    | Format version, currently `1`                           |
+//! | 5      | 1    | Flags, bit 0 means authenticated, other bits are zero   |
+//! | 6      | 2    | Reserved, zero                                          |
+//! | 8      | 4    | Chunk size (zero unless authenticated)                  |
+//! | 12     | 8    | Key id, opaque to this crate                            |
+//! | 20     | 8    | Initialization vector                                   |
+//!
+//! Keystream is produced by `Rabbit::new_iv(key, iv)`.
+//!
+//! Without authentication the header is followed by plaintext XORed with keystream.
+//!
+//! With authentication the first 32 bytes of keystream are used as an HMAC-SHA256 key and
+//! the rest of keystream encrypts data. Body is a sequence of chunks, each one is ciphertext
+//! followed by a 16-byte tag. Every chunk but the last carries exactly `chunk size` bytes
+//! of ciphertext, the last one carries less (possibly zero) bytes. Tag is the HMAC-SHA256,
+//! truncated to 16 bytes, of header, chunk index (u64), final chunk flag (u8, `1` for the
+//! last chunk) and chunk ciphertext. So header, chunk order and truncation are authenticated.
+//!
+//! ```ignore
+//! let header = Header::new(key_id, iv).authenticated(DEFAULT_CHUNK_SIZE);
+//! let mut encryptor = FileEncryptor::new(header, &key, file)?;
+//! encryptor.write_all(data)?;
+//! let file = encryptor.finish()?;
+//!
+//! let header = Header::read_from(&mut file)?;
+//! let key = keys.get(header.key_id());
+//! let mut decryptor = FileDecryptor::with_header(header, &key, file);
+//! decryptor.read_to_end(&mut data)?;
+//! ```
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::io::{Read, Write};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use super::{InitVec, Key, Rabbit};
+
+/// Magic bytes at the beginning of every container.
+pub const MAGIC: [u8; 4] = *b"RBBT";
+/// Current format version.
+pub const VERSION: u8 = 1;
+/// Length of encoded header.
+pub const HEADER_LEN: usize = 28;
+/// Length of chunk authentication tag.
+pub const TAG_LEN: usize = 16;
+/// Recommended chunk size for authenticated containers.
+pub const DEFAULT_CHUNK_SIZE: u32 = 64 * 1024;
+/// Largest chunk size accepted by the decryptor.
+pub const MAX_CHUNK_SIZE: u32 = 16 * 1024 * 1024;
+
+const FLAG_AUTHENTICATED: u8 = 0b1;
+const MAC_KEY_LEN: usize = 32;
+
+/// Container errors.
+#[derive(Debug)]
+pub enum Error {
+    /// Underlying reader or writer failed.
+    Io(io::Error),
+    /// Data doesn't start with `MAGIC`.
+    BadMagic,
+    /// Format version isn't supported.
+    UnsupportedVersion(u8),
+    /// Unknown flags are set.
+    UnsupportedFlags(u8),
+    /// Reserved header bytes aren't zero.
+    NonZeroReserved,
+    /// Chunk size is zero or above `MAX_CHUNK_SIZE` for authenticated container
+    /// or isn't zero for unauthenticated one.
+    InvalidChunkSize(u32),
+    /// Data ends before the end of header.
+    TruncatedHeader,
+    /// Authenticated body ends before its final chunk.
+    Truncated,
+    /// Chunk with given index failed authentication.
+    BadTag(u64),
+    /// Data follows the final chunk.
+    TrailingData,
+    /// An earlier read of authenticated body failed, see `FileDecryptor`.
+    Poisoned,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref err) => write!(f, "I/O error: {}", err),
+            Error::BadMagic => write!(f, "not a rabbit container"),
+            Error::UnsupportedVersion(version) => write!(f, "unsupported version {}", version),
+            Error::UnsupportedFlags(flags) => write!(f, "unsupported flags 0x{:02X}", flags),
+            Error::NonZeroReserved => write!(f, "reserved header bytes are not zero"),
+            Error::InvalidChunkSize(size) => write!(f, "invalid chunk size {}", size),
+            Error::TruncatedHeader => write!(f, "header is truncated"),
+            Error::Truncated => write!(f, "data is truncated"),
+            Error::BadTag(index) => write!(f, "chunk {} failed authentication", index),
+            Error::TrailingData => write!(f, "data follows the final chunk"),
+            Error::Poisoned => write!(f, "an earlier read failed"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Unwraps container error previously converted into `io::Error`.
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        if err.get_ref().is_some_and(|inner| inner.is::<Error>()) {
+            let inner = err.into_inner().unwrap();
+            return *inner.downcast::<Error>().unwrap();
+        }
+        Error::Io(err)
+    }
+}
+
+/// Non-I/O errors become `io::ErrorKind::InvalidData`.
+impl From<Error> for io::Error {
+    fn from(err: Error) -> io::Error {
+        match err {
+            Error::Io(err) => err,
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+/// Container header.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Header {
+    chunk_size: Option<u32>,
+    key_id: [u8; 8],
+    iv: InitVec,
+}
+
+impl Header {
+    /// Header of unauthenticated container.
+    pub fn new(key_id: [u8; 8], iv: InitVec) -> Header {
+        Header {
+            chunk_size: None,
+            key_id,
+            iv,
+        }
+    }
+
+    /// Enables authentication with given chunk size.
+    /// Asserts that `0 < chunk_size <= MAX_CHUNK_SIZE`.
+    pub fn authenticated(mut self, chunk_size: u32) -> Header {
+        assert!(chunk_size > 0 && chunk_size <= MAX_CHUNK_SIZE);
+        self.chunk_size = Some(chunk_size);
+        self
+    }
+
+    /// Chunk size if container is authenticated.
+    pub fn chunk_size(&self) -> Option<u32> {
+        self.chunk_size
+    }
+
+    pub fn key_id(&self) -> &[u8; 8] {
+        &self.key_id
+    }
+
+    pub fn iv(&self) -> &InitVec {
+        &self.iv
+    }
+
+    pub fn to_bytes(&self) -> [u8; HEADER_LEN] {
+        let mut bytes = [0; HEADER_LEN];
+        bytes[0..4].copy_from_slice(&MAGIC);
+        bytes[4] = VERSION;
+        if let Some(chunk_size) = self.chunk_size {
+            bytes[5] = FLAG_AUTHENTICATED;
+            bytes[8..12].copy_from_slice(&chunk_size.to_le_bytes());
+        }
+        bytes[12..20].copy_from_slice(&self.key_id);
+        bytes[20..28].copy_from_slice(&self.iv[..]);
+        bytes
+    }
+
+    /// Parses and validates header.
+    pub fn from_bytes(bytes: &[u8; HEADER_LEN]) -> Result<Header, Error> {
+        if bytes[0..4] != MAGIC {
+            return Err(Error::BadMagic);
+        }
+        if bytes[4] != VERSION {
+            return Err(Error::UnsupportedVersion(bytes[4]));
+        }
+        let flags = bytes[5];
+        if flags & !FLAG_AUTHENTICATED != 0 {
+            return Err(Error::UnsupportedFlags(flags));
+        }
+        if bytes[6] != 0 || bytes[7] != 0 {
+            return Err(Error::NonZeroReserved);
+        }
+        let chunk_size = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+        let chunk_size = if flags & FLAG_AUTHENTICATED != 0 {
+            if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+                return Err(Error::InvalidChunkSize(chunk_size));
+            }
+            Some(chunk_size)
+        } else {
+            if chunk_size != 0 {
+                return Err(Error::InvalidChunkSize(chunk_size));
+            }
+            None
+        };
+        let mut key_id = [0; 8];
+        key_id.copy_from_slice(&bytes[12..20]);
+        Ok(Header {
+            chunk_size,
+            key_id,
+            iv: InitVec::from(&bytes[20..28]),
+        })
+    }
+
+    /// Reads and validates header.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Header, Error> {
+        let mut bytes = [0; HEADER_LEN];
+        if read_full(reader, &mut bytes)? < HEADER_LEN {
+            return Err(Error::TruncatedHeader);
+        }
+        Header::from_bytes(&bytes)
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.to_bytes())
+    }
+}
+
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut count = 0;
+    while count < buf.len() {
+        match reader.read(&mut buf[count..]) {
+            Ok(0) => break,
+            Ok(n) => count += n,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(count)
+}
+
+struct Auth {
+    mac: Hmac<Sha256>,
+    header: [u8; HEADER_LEN],
+    chunk_size: usize,
+    index: u64,
+}
+
+impl Auth {
+    /// Takes MAC key from the beginning of `rabbit` keystream.
+    fn new(header: &Header, chunk_size: u32, rabbit: &mut Rabbit) -> Auth {
+        let mut mac_key = [0; MAC_KEY_LEN];
+        rabbit.encrypt_inplace(&mut mac_key);
+        Auth {
+            mac: Hmac::new_from_slice(&mac_key).expect("HMAC accepts keys of any length"),
+            header: header.to_bytes(),
+            chunk_size: chunk_size as usize,
+            index: 0,
+        }
+    }
+
+    fn chunk_mac(&self, last: bool, ciphertext: &[u8]) -> Hmac<Sha256> {
+        let mut mac = self.mac.clone();
+        mac.update(&self.header);
+        mac.update(&self.index.to_le_bytes());
+        mac.update(&[last as u8]);
+        mac.update(ciphertext);
+        mac
+    }
+}
+
+/// Writes container header and encrypted body to the inner writer.
+///
+/// `finish` must be called to write the final chunk of an authenticated container.
+/// Dropping it without `finish` leaves a truncated file, which is only detected when it
+/// is decrypted (`Error::Truncated`).
+pub struct FileEncryptor<W: Write> {
+    inner: W,
+    rabbit: Rabbit,
+    auth: Option<Auth>,
+    chunk: Vec<u8>,
+}
+
+impl<W: Write> FileEncryptor<W> {
+    /// Writes `header` and prepares to encrypt with `key`.
+    pub fn new(header: Header, key: &Key, mut inner: W) -> io::Result<FileEncryptor<W>> {
+        header.write_to(&mut inner)?;
+        let mut rabbit = Rabbit::new_iv(key, header.iv());
+        let auth = header.chunk_size().map(|size| Auth::new(&header, size, &mut rabbit));
+        let chunk = Vec::with_capacity(auth.as_ref().map_or(0, |auth| auth.chunk_size + TAG_LEN));
+        Ok(FileEncryptor {
+            inner,
+            rabbit,
+            auth,
+            chunk,
+        })
+    }
+
+    /// Writes remaining data and returns inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.auth.is_some() {
+            self.write_chunk(true)?;
+        }
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    fn write_chunk(&mut self, last: bool) -> io::Result<()> {
+        let auth = self.auth.as_mut().expect("authenticated");
+        self.rabbit.encrypt_inplace(&mut self.chunk);
+        let tag = auth.chunk_mac(last, &self.chunk).finalize().into_bytes();
+        self.chunk.extend_from_slice(&tag[..TAG_LEN]);
+        self.inner.write_all(&self.chunk)?;
+        self.chunk.clear();
+        auth.index += 1;
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for FileEncryptor<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let chunk_size = match self.auth {
+            Some(ref auth) => auth.chunk_size,
+            None => {
+                let mut vec = vec![0; buf.len()];
+                self.rabbit.encrypt(buf, &mut vec[..]);
+                self.inner.write_all(&vec[..])?;
+                return Ok(buf.len());
+            }
+        };
+        let mut data = buf;
+        while !data.is_empty() {
+            let count = ::std::cmp::min(chunk_size - self.chunk.len(), data.len());
+            self.chunk.extend_from_slice(&data[..count]);
+            data = &data[count..];
+            if self.chunk.len() == chunk_size {
+                self.write_chunk(false)?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    /// Flushes inner writer. Partial chunk is kept until it is full or `finish` is called.
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reads and decrypts container body from the inner reader.
+///
+/// Read errors caused by corrupted data are `io::ErrorKind::InvalidData` wrapping
+/// `container::Error`, which `Error::from` unwraps. Authenticated chunks are verified
+/// before any of their plaintext is returned. Data appended to an authenticated
+/// container extends its final chunk, which then fails authentication; if the inner
+/// reader signals end of data before more data, that is `Error::TrailingData`.
+///
+/// Any error while reading an authenticated chunk, I/O errors included, poisons the
+/// decryptor: the chunk is discarded and later reads return `Error::Poisoned`.
+pub struct FileDecryptor<R: Read> {
+    inner: R,
+    rabbit: Rabbit,
+    auth: Option<Auth>,
+    chunk: Vec<u8>,
+    pos: usize,
+    done: bool,
+    poisoned: bool,
+}
+
+impl<R: Read> FileDecryptor<R> {
+    /// Reads and validates header, then prepares to decrypt with `key`.
+    pub fn new(key: &Key, mut inner: R) -> Result<FileDecryptor<R>, Error> {
+        let header = Header::read_from(&mut inner)?;
+        Ok(FileDecryptor::with_header(header, key, inner))
+    }
+
+    /// Prepares to decrypt body that follows already read `header`.
+    pub fn with_header(header: Header, key: &Key, inner: R) -> FileDecryptor<R> {
+        let mut rabbit = Rabbit::new_iv(key, header.iv());
+        let auth = header.chunk_size().map(|size| Auth::new(&header, size, &mut rabbit));
+        FileDecryptor {
+            inner,
+            rabbit,
+            auth,
+            chunk: Vec::new(),
+            pos: 0,
+            done: false,
+            poisoned: false,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn read_chunk(&mut self) -> Result<(), Error> {
+        let auth = self.auth.as_mut().expect("authenticated");
+        self.chunk.resize(auth.chunk_size + TAG_LEN, 0);
+        let count = read_full(&mut self.inner, &mut self.chunk)?;
+        if count < TAG_LEN {
+            return Err(Error::Truncated);
+        }
+        let last = count < self.chunk.len();
+        let (ciphertext, tag) = self.chunk[..count].split_at(count - TAG_LEN);
+        if auth.chunk_mac(last, ciphertext).verify_truncated_left(tag).is_err() {
+            return Err(Error::BadTag(auth.index));
+        }
+        if last && read_full(&mut self.inner, &mut [0])? > 0 {
+            return Err(Error::TrailingData);
+        }
+        self.chunk.truncate(count - TAG_LEN);
+        self.rabbit.decrypt_inplace(&mut self.chunk);
+        auth.index += 1;
+        self.pos = 0;
+        self.done = last;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for FileDecryptor<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.auth.is_none() {
+            let count = self.inner.read(buf)?;
+            self.rabbit.decrypt_inplace(&mut buf[0..count]);
+            return Ok(count);
+        }
+        if self.poisoned {
+            return Err(Error::Poisoned.into());
+        }
+        while self.pos == self.chunk.len() {
+            if self.done || buf.is_empty() {
+                return Ok(0);
+            }
+            if let Err(err) = self.read_chunk() {
+                // Unverified ciphertext must not be returned by later reads.
+                self.chunk.clear();
+                self.pos = 0;
+                self.poisoned = true;
+                return Err(err.into());
+            }
+        }
+        let count = ::std::cmp::min(buf.len(), self.chunk.len() - self.pos);
+        buf[..count].copy_from_slice(&self.chunk[self.pos..self.pos + count]);
+        self.pos += count;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{self, Read, Write};
+
+    use super::{Error, FileDecryptor, FileEncryptor, Header, HEADER_LEN, MAX_CHUNK_SIZE, TAG_LEN};
+    use super::super::{InitVec, Key, Rabbit};
+
+    fn key() -> Key {
+        Key::from([0x5A; 16])
+    }
+
+    fn encrypt(header: Header, data: &[u8], write_size: usize) -> Vec<u8> {
+        let mut encryptor = FileEncryptor::new(header, &key(), Vec::new()).unwrap();
+        for piece in data.chunks(write_size) {
+            encryptor.write_all(piece).unwrap();
+        }
+        encryptor.finish().unwrap()
+    }
+
+    fn decrypt(file: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut decryptor = FileDecryptor::new(&key(), file)?;
+        let mut data = Vec::new();
+        decryptor.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    fn authenticated() -> Header {
+        Header::new(*b"key-id-1", InitVec::from(7)).authenticated(16)
+    }
+
+    #[test]
+    fn header_roundtrip() {
+        let header = authenticated();
+        let bytes = header.to_bytes();
+        assert_eq!(&bytes[..12], b"RBBT\x01\x01\x00\x00\x10\x00\x00\x00");
+        assert_eq!(Header::from_bytes(&bytes).unwrap(), header);
+        let header = Header::new([1; 8], InitVec::from(0x0102030405060708));
+        assert_eq!(Header::from_bytes(&header.to_bytes()).unwrap(), header);
+    }
+
+    #[test]
+    fn header_validation() {
+        let good = authenticated().to_bytes();
+        let check = |offset: usize, value: u8| {
+            let mut bytes = good;
+            bytes[offset] = value;
+            Header::from_bytes(&bytes).unwrap_err()
+        };
+        match check(0, b'X') { Error::BadMagic => (), err => panic!("{:?}", err) }
+        match check(4, 2) { Error::UnsupportedVersion(2) => (), err => panic!("{:?}", err) }
+        match check(5, 0b11) { Error::UnsupportedFlags(0b11) => (), err => panic!("{:?}", err) }
+        match check(7, 1) { Error::NonZeroReserved => (), err => panic!("{:?}", err) }
+        match check(8, 0) { Error::InvalidChunkSize(0) => (), err => panic!("{:?}", err) }
+        match check(5, 0) { Error::InvalidChunkSize(16) => (), err => panic!("{:?}", err) }
+
+        let mut bytes = good;
+        bytes[8..12].copy_from_slice(&(MAX_CHUNK_SIZE + 1).to_le_bytes());
+        match Header::from_bytes(&bytes) {
+            Err(Error::InvalidChunkSize(_)) => (),
+            other => panic!("{:?}", other),
+        }
+
+        match decrypt(&good[..HEADER_LEN - 1]) {
+            Err(Error::TruncatedHeader) => (),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn unauthenticated_roundtrip() {
+        let data: Vec<u8> = (0..100u8).collect();
+        let header = Header::new([0; 8], InitVec::from(7));
+        let file = encrypt(header, &data, 7);
+        assert_eq!(file.len(), HEADER_LEN + data.len());
+
+        let mut expected = data.clone();
+        Rabbit::new_iv(&key(), &InitVec::from(7)).encrypt_inplace(&mut expected);
+        assert_eq!(&file[HEADER_LEN..], &expected[..]);
+
+        assert_eq!(decrypt(&file).unwrap(), data);
+    }
+
+    #[test]
+    fn authenticated_roundtrip() {
+        for len in [0, 1, 15, 16, 17, 32, 100].iter().cloned() {
+            let data: Vec<u8> = (0..len as u8).collect();
+            for write_size in [1, 5, 16, 64].iter().cloned() {
+                let file = encrypt(authenticated(), &data, write_size);
+                let chunks = len / 16 + 1;
+                assert_eq!(file.len(), HEADER_LEN + len + chunks * TAG_LEN);
+                assert_eq!(decrypt(&file).unwrap(), data);
+            }
+        }
+    }
+
+    #[test]
+    fn authenticated_corruption() {
+        let data = [0xAB; 40];
+        let file = encrypt(authenticated(), &data, 40);
+
+        let mut flipped = file.clone();
+        flipped[HEADER_LEN + 16 + TAG_LEN] ^= 1;
+        match decrypt(&flipped) {
+            Err(Error::BadTag(1)) => (),
+            other => panic!("{:?}", other),
+        }
+
+        let mut key_id = file.clone();
+        key_id[12] ^= 1;
+        match decrypt(&key_id) {
+            Err(Error::BadTag(0)) => (),
+            other => panic!("{:?}", other),
+        }
+
+        let full_chunks = HEADER_LEN + 2 * (16 + TAG_LEN);
+        match decrypt(&file[..full_chunks]) {
+            Err(Error::Truncated) => (),
+            other => panic!("{:?}", other),
+        }
+        match decrypt(&file[..full_chunks + 3]) {
+            Err(Error::Truncated) => (),
+            other => panic!("{:?}", other),
+        }
+        match decrypt(&file[..file.len() - 1]) {
+            Err(Error::BadTag(2)) => (),
+            other => panic!("{:?}", other),
+        }
+
+        let mut decryptor = FileDecryptor::new(&Key::from([0; 16]), &file[..]).unwrap();
+        let err = decryptor.read(&mut [0; 8]).unwrap_err();
+        match Error::from(err) {
+            Error::BadTag(0) => (),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    /// Reads `parts` one after another, signalling end of data after each.
+    struct Parts<'a> {
+        parts: Vec<&'a [u8]>,
+    }
+
+    impl<'a> Read for Parts<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.parts.is_empty() {
+                return Ok(0);
+            }
+            let count = self.parts[0].read(buf)?;
+            if count == 0 {
+                self.parts.remove(0);
+            }
+            Ok(count)
+        }
+    }
+
+    #[test]
+    fn authenticated_trailing_data() {
+        let file = encrypt(authenticated(), &[0xCD; 40], 40);
+        let mut appended = file.clone();
+        appended.extend_from_slice(b"junk");
+        match decrypt(&appended) {
+            Err(Error::BadTag(2)) => (),
+            other => panic!("{:?}", other),
+        }
+
+        let reader = Parts { parts: vec![&file[..], b"junk"] };
+        let mut decryptor = FileDecryptor::new(&key(), reader).unwrap();
+        let err = decryptor.read_to_end(&mut Vec::new()).unwrap_err();
+        match Error::from(err) {
+            Error::TrailingData => (),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    /// Reads `file` until the first error, then reads again.
+    fn read_after_error(file: &[u8]) -> (Error, io::Result<usize>) {
+        let mut decryptor = FileDecryptor::new(&key(), file).unwrap();
+        let mut buf = [0; 8];
+        loop {
+            match decryptor.read(&mut buf) {
+                Ok(0) => panic!("no error"),
+                Ok(_) => {}
+                Err(err) => return (Error::from(err), decryptor.read(&mut buf)),
+            }
+        }
+    }
+
+    #[test]
+    fn authenticated_poisoned_after_error() {
+        let file = encrypt(authenticated(), &[0xEF; 40], 40);
+        let mut flipped = file.clone();
+        flipped[HEADER_LEN + 16 + TAG_LEN] ^= 1;
+        let truncated = &file[..HEADER_LEN + 2 * (16 + TAG_LEN) + 3];
+        for (file, expected) in [(&flipped[..], "BadTag(1)"), (truncated, "Truncated")].iter() {
+            let (err, again) = read_after_error(file);
+            assert_eq!(format!("{:?}", err), *expected);
+            match again.map_err(Error::from) {
+                Err(Error::Poisoned) => (),
+                other => panic!("{:?}", other),
+            }
+        }
+    }
+}
@@ -0,0 +1,269 @@
+//! Interoperability with CryptoJS `Rabbit` and `RabbitLegacy`.
+//!
+//! CryptoJS keeps data in big-endian 32-bit words. `Rabbit` swaps key, IV and keystream
+//! words to little-endian, so its keystream is the one of RFC 4503 and of `Rabbit` here.
+//! `RabbitLegacy` is kept by CryptoJS for data of its earlier Rabbit, which skips the swap
+//! of key words: key setup sees every 4-byte group of the key reversed. IV and keystream
+//! are handled as in `Rabbit`.
+//!
+//! `CryptoJS.Rabbit.encrypt(message, key, { iv })` with a 16-byte key `WordArray` (and an
+//! optional 8-byte IV) gives raw ciphertext, Base64 in its string form; see
+//! `encrypt_with_key`. With a passphrase instead of a key, key and IV are derived by
+//! OpenSSL `EVP_BytesToKey` with MD5, one iteration and random 8-byte salt, and the string
+//! form is Base64 of `Salted__`, salt and ciphertext; see `encrypt` and `decrypt`.
+//!
+//! ```ignore
+//! // CryptoJS.RabbitLegacy.encrypt("message", "passphrase").toString()
+//! let plaintext = cryptojs::decrypt(Variant::RabbitLegacy, b"passphrase", &data)?;
+//! ```
+
+use std::error;
+use std::fmt;
+
+use base64::alphabet;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig};
+use base64::engine::DecodePaddingMode;
+use base64::Engine;
+use md5::{Digest, Md5};
+
+use super::{InitVec, Key, Rabbit};
+
+/// Prefix of passphrase-encrypted data, followed by 8-byte salt.
+pub const SALTED: [u8; 8] = *b"Salted__";
+
+/// Standard alphabet, padding is optional on decoding like in CryptoJS.
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// CryptoJS cipher.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Variant {
+    /// `CryptoJS.Rabbit`, same as RFC 4503.
+    Rabbit,
+    /// `CryptoJS.RabbitLegacy`.
+    RabbitLegacy,
+}
+
+/// CryptoJS decoding errors.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Error {
+    /// Data isn't valid Base64.
+    InvalidBase64,
+    /// Data doesn't start with `SALTED` and salt.
+    NotSalted,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::InvalidBase64 => write!(f, "data is not valid Base64"),
+            Error::NotSalted => write!(f, "data does not start with \"Salted__\" and salt"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// Returns cipher with keystream of given CryptoJS `variant`.
+pub fn rabbit(variant: Variant, key: &Key, iv: Option<&InitVec>) -> Rabbit {
+    let key = match variant {
+        Variant::Rabbit => key.clone(),
+        Variant::RabbitLegacy => {
+            let mut key = **key;
+            for word in key.chunks_mut(4) {
+                word.reverse();
+            }
+            Key::from(key)
+        }
+    };
+    match iv {
+        Some(iv) => Rabbit::new_iv(&key, iv),
+        None => Rabbit::new(&key),
+    }
+}
+
+/// OpenSSL `EVP_BytesToKey` with MD5 and one iteration, as used by CryptoJS for
+/// passphrases.
+pub fn evp_bytes_to_key(passphrase: &[u8], salt: &[u8; 8]) -> (Key, InitVec) {
+    let first = Md5::new().chain_update(passphrase).chain_update(salt).finalize();
+    let second = Md5::new()
+        .chain_update(first)
+        .chain_update(passphrase)
+        .chain_update(salt)
+        .finalize();
+    (Key::from(&first[..]), InitVec::from(&second[..8]))
+}
+
+/// Encrypts `data` like `CryptoJS[variant].encrypt(data, passphrase)` with given `salt`,
+/// returns its string form. Salt must be random.
+pub fn encrypt(variant: Variant, passphrase: &[u8], salt: &[u8; 8], data: &[u8]) -> String {
+    let (key, iv) = evp_bytes_to_key(passphrase, salt);
+    let mut out = Vec::with_capacity(SALTED.len() + salt.len() + data.len());
+    out.extend_from_slice(&SALTED);
+    out.extend_from_slice(salt);
+    out.extend_from_slice(data);
+    rabbit(variant, &key, Some(&iv)).encrypt_inplace(&mut out[16..]);
+    BASE64.encode(out)
+}
+
+/// Decrypts string form of `CryptoJS[variant].encrypt(message, passphrase)`.
+///
+/// Wrong passphrase isn't detected, it gives garbage.
+pub fn decrypt(variant: Variant, passphrase: &[u8], data: &str) -> Result<Vec<u8>, Error> {
+    let mut data = decode(data)?;
+    if data.len() < 16 || data[..8] != SALTED {
+        return Err(Error::NotSalted);
+    }
+    let mut salt = [0; 8];
+    salt.copy_from_slice(&data[8..16]);
+    let (key, iv) = evp_bytes_to_key(passphrase, &salt);
+    let mut data = data.split_off(16);
+    rabbit(variant, &key, Some(&iv)).decrypt_inplace(&mut data);
+    Ok(data)
+}
+
+/// Encrypts `data` like `CryptoJS[variant].encrypt(data, key, { iv })`, returns its string
+/// form.
+pub fn encrypt_with_key(variant: Variant, key: &Key, iv: Option<&InitVec>, data: &[u8]) -> String {
+    BASE64.encode(rabbit(variant, key, iv).encrypt_to_vec(data))
+}
+
+/// Decrypts string form of `CryptoJS[variant].encrypt(message, key, { iv })`.
+pub fn decrypt_with_key(
+    variant: Variant,
+    key: &Key,
+    iv: Option<&InitVec>,
+    data: &str,
+) -> Result<Vec<u8>, Error> {
+    let mut data = decode(data)?;
+    rabbit(variant, key, iv).decrypt_inplace(&mut data);
+    Ok(data)
+}
+
+/// Decodes Base64, ignoring whitespace such as line breaks.
+fn decode(data: &str) -> Result<Vec<u8>, Error> {
+    let data: Vec<u8> = data.bytes().filter(|byte| !byte.is_ascii_whitespace()).collect();
+    BASE64.decode(data).map_err(|_| Error::InvalidBase64)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::Path;
+
+    use super::{
+        decrypt, decrypt_with_key, encrypt, encrypt_with_key, evp_bytes_to_key, rabbit, Error,
+        Variant,
+    };
+    use super::super::{InitVec, Key};
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// Blocks of `name = value` lines from `tests/vectors/cryptojs.txt`.
+    fn vectors() -> Vec<HashMap<String, String>> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/vectors/cryptojs.txt");
+        let text = fs::read_to_string(path).unwrap();
+        let mut vectors = Vec::new();
+        for block in text.split("\n\n") {
+            let vector: HashMap<String, String> = block
+                .lines()
+                .filter(|line| !line.starts_with('#'))
+                .map(|line| {
+                    let (name, value) = line.split_once('=').unwrap();
+                    (name.trim().to_owned(), value.trim().to_owned())
+                })
+                .collect();
+            if !vector.is_empty() {
+                vectors.push(vector);
+            }
+        }
+        vectors
+    }
+
+    fn variant(name: &str) -> Variant {
+        match name {
+            "Rabbit" => Variant::Rabbit,
+            "RabbitLegacy" => Variant::RabbitLegacy,
+            _ => panic!("unknown variant {}", name),
+        }
+    }
+
+    #[test]
+    fn vectors_with_key() {
+        let mut count = 0;
+        for vector in vectors().iter().filter(|vector| vector.contains_key("key")) {
+            let variant = variant(&vector["variant"]);
+            let key = Key::from(&hex(&vector["key"])[..]);
+            let iv = vector.get("iv").map(|iv| InitVec::from(&hex(iv)[..]));
+            let plaintext = hex(&vector["plaintext"]);
+            let ciphertext = hex(&vector["ciphertext"]);
+
+            let mut cipher = rabbit(variant, &key, iv.as_ref());
+            assert_eq!(cipher.encrypt_to_vec(&plaintext), ciphertext, "{:?}", vector);
+            let encoded = encrypt_with_key(variant, &key, iv.as_ref(), &plaintext);
+            let decrypted = decrypt_with_key(variant, &key, iv.as_ref(), &encoded).unwrap();
+            assert_eq!(decrypted, plaintext);
+            count += 1;
+        }
+        assert_eq!(count, 16);
+    }
+
+    #[test]
+    fn vectors_with_passphrase() {
+        let mut count = 0;
+        for vector in vectors().iter().filter(|vector| vector.contains_key("passphrase")) {
+            let variant = variant(&vector["variant"]);
+            let passphrase = vector["passphrase"].as_bytes();
+            let plaintext = hex(&vector["plaintext"]);
+            let openssl = &vector["openssl"];
+
+            assert_eq!(decrypt(variant, passphrase, openssl).unwrap(), plaintext, "{:?}", vector);
+            let mut salt = [0; 8];
+            salt.copy_from_slice(&hex(&vector["salt"]));
+            assert_eq!(&encrypt(variant, passphrase, &salt, &plaintext), openssl);
+            count += 1;
+        }
+        assert_eq!(count, 18);
+    }
+
+    #[test]
+    fn rabbit_is_rfc_4503() {
+        // RFC 4503 A.1, second key.
+        let key = Key::from(&hex("ACC351DCF162FC3BFE363D2E29132891")[..]);
+        let stream = rabbit(Variant::Rabbit, &key, None).encrypt_to_vec(&[0; 16]);
+        assert_eq!(stream, hex("9C51E28784C37FE9A127F63EC8F32D3D"));
+        let legacy = rabbit(Variant::RabbitLegacy, &key, None).encrypt_to_vec(&[0; 16]);
+        assert_ne!(legacy, stream);
+    }
+
+    #[test]
+    fn evp_bytes_to_key_matches_openssl() {
+        // openssl enc -aes-128-cbc -md md5 -k password -S 0001020304050607 -P
+        let (key, iv) = evp_bytes_to_key(b"password", &[0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(&key[..], &hex("B03096345E805D3AA4392D2E72791DFB")[..]);
+        assert_eq!(&iv[..], &hex("13E12D3F61094A3F")[..]);
+    }
+
+    #[test]
+    fn rejects_malformed_data() {
+        assert_eq!(decrypt(Variant::Rabbit, b"", "not base64!"), Err(Error::InvalidBase64));
+        assert_eq!(decrypt(Variant::Rabbit, b"", "U2FsdGVkX18="), Err(Error::NotSalted));
+        assert_eq!(decrypt(Variant::Rabbit, b"", "AAAAAAAAAAAAAAAAAAAAAA=="), Err(Error::NotSalted));
+    }
+
+    #[test]
+    fn decrypt_ignores_line_breaks_and_padding() {
+        let data = encrypt(Variant::Rabbit, b"pass", &[7; 8], b"some plaintext of 31 bytes long");
+        let wrapped = format!("{}\n{}\n", &data[..20], data[20..].trim_end_matches('='));
+        let plaintext = decrypt(Variant::Rabbit, b"pass", &wrapped).unwrap();
+        assert_eq!(plaintext, b"some plaintext of 31 bytes long");
+    }
+}
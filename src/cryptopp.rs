@@ -0,0 +1,87 @@
+//! Interoperability with Crypto++ `Rabbit` and `RabbitWithIV`.
+//!
+//! Crypto++ reads key and IV as little-endian 32-bit words and writes keystream words
+//! little-endian (`GetBlock<word32, LittleEndian>` and `PutWord(.., LITTLE_ENDIAN_ORDER, ..)`
+//! in `rabbit.cpp`), as the RFC 4503 reference code and `Rabbit` here do. Key and IV bytes
+//! are passed as they are, no reordering is needed:
+//!
+//! | Crypto++                                          | Here                                  |
+//! |---------------------------------------------------|---------------------------------------|
+//! | `Rabbit::Encryption::SetKey(key, 16)`             | `rabbit(key)`, `Rabbit::new`          |
+//! | `RabbitWithIV::Encryption::SetKeyWithIV(key, 16, iv, 8)` | `rabbit_with_iv(key, iv)`, `Rabbit::new_iv` |
+//! | `Resynchronize(iv, 8)`                            | `Rabbit::reinit`                      |
+//! | `ProcessData(out, in, n)`                         | `Rabbit::encrypt`                     |
+//! | `GenerateBlock(out, n)`                           | `Rabbit::encrypt_inplace` on zeros    |
+//!
+//! Only RFC 4503 prints key, IV and keystream most significant byte first; take its
+//! byte strings reversed.
+//!
+//! The mapping follows Crypto++ sources. Crypto++ output is checked in CI only:
+//! `tests/vectors/cryptopp/generate.cpp` is built against Crypto++ there and its output
+//! is run through these functions (see `tests/vectors/README.md`).
+
+#[cfg(not(feature = "nostd"))]
+use std::error;
+#[cfg(not(feature = "nostd"))]
+use std::fmt;
+
+#[cfg(feature = "nostd")]
+use core::fmt;
+
+use super::{InitVec, Key, Rabbit};
+
+/// Key or IV length rejected, as by Crypto++ `InvalidKeyLength` and `InvalidArgument`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Error {
+    /// Key isn't 16 bytes.
+    InvalidKeyLength(usize),
+    /// IV isn't 8 bytes.
+    InvalidIvLength(usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::InvalidKeyLength(len) => write!(f, "{} is not a valid key length", len),
+            Error::InvalidIvLength(len) => write!(f, "{} is not a valid IV length", len),
+        }
+    }
+}
+
+#[cfg(not(feature = "nostd"))]
+impl error::Error for Error {}
+
+fn key(key: &[u8]) -> Result<Key, Error> {
+    if key.len() != 16 {
+        return Err(Error::InvalidKeyLength(key.len()));
+    }
+    Ok(Key::from(key))
+}
+
+/// Same keystream as Crypto++ `Rabbit` keyed with `key`.
+pub fn rabbit(key: &[u8]) -> Result<Rabbit, Error> {
+    Ok(Rabbit::new(&self::key(key)?))
+}
+
+/// Same keystream as Crypto++ `RabbitWithIV` keyed with `key` and `iv`.
+pub fn rabbit_with_iv(key: &[u8], iv: &[u8]) -> Result<Rabbit, Error> {
+    let key = self::key(key)?;
+    if iv.len() != 8 {
+        return Err(Error::InvalidIvLength(iv.len()));
+    }
+    Ok(Rabbit::new_iv(&key, &InitVec::from(iv)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{rabbit, rabbit_with_iv, Error};
+
+    #[test]
+    fn rejects_invalid_lengths() {
+        assert_eq!(rabbit(&[0; 15]).err(), Some(Error::InvalidKeyLength(15)));
+        assert_eq!(rabbit(&[0; 32]).err(), Some(Error::InvalidKeyLength(32)));
+        assert_eq!(rabbit_with_iv(&[0; 17], &[0; 8]).err(), Some(Error::InvalidKeyLength(17)));
+        assert_eq!(rabbit_with_iv(&[0; 16], &[0; 16]).err(), Some(Error::InvalidIvLength(16)));
+        assert!(rabbit_with_iv(&[0; 16], &[0; 8]).is_ok());
+    }
+}
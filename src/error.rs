@@ -0,0 +1,39 @@
+#[cfg(not(feature = "nostd"))]
+use std::error;
+#[cfg(not(feature = "nostd"))]
+use std::fmt;
+
+#[cfg(feature = "nostd")]
+use core::fmt;
+
+/// Errors reported by non-panicking methods and by the IV-tracking and limiting wrappers.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Error {
+    /// Initialization vector was already used with this key.
+    IvReused,
+    /// Every counter value of an `IvSequence` was already issued.
+    IvSpaceExhausted,
+    /// Configured per-key message limit was reached.
+    MessageLimitReached,
+    /// Configured keystream limit for this key/IV was reached.
+    KeystreamLimitReached,
+    /// Output buffer length does not fit input data length.
+    LengthMismatch { data: usize, buf: usize },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::IvReused => write!(f, "initialization vector was already used with this key"),
+            Error::IvSpaceExhausted => write!(f, "initialization vector space is exhausted"),
+            Error::MessageLimitReached => write!(f, "message limit for this key is reached"),
+            Error::KeystreamLimitReached => write!(f, "keystream limit for this key/IV is reached"),
+            Error::LengthMismatch { data, buf } => {
+                write!(f, "buffer length {} does not fit data length {}", buf, data)
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "nostd"))]
+impl error::Error for Error {}
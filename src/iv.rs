@@ -0,0 +1,287 @@
+#[cfg(not(feature = "nostd"))]
+use std::collections::HashSet;
+
+#[cfg(feature = "rand_core")]
+use rand_core::{OsRng, RngCore};
+
+use super::{Error, InitVec};
+#[cfg(not(feature = "nostd"))]
+use super::{Key, Rabbit};
+
+/// Generator of unique counter-based initialization vectors for a single key.
+///
+/// Without prefix the whole 64-bit IV is a counter. With prefix the high 32 bits are
+/// fixed and the low 32 bits are a counter. The prefix should be random per key:
+/// `with_prefix` takes it from the caller, who must supply the randomness, and
+/// `with_random_prefix` (`rand_core` feature) draws it from the operating system.
+///
+/// ```ignore
+/// let mut ivs = IvSequence::with_random_prefix().limit(1 << 20);
+/// let iv = ivs.next_iv()?; // Err(Error::MessageLimitReached) after 2^20 IVs
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct IvSequence {
+    prefix: u64,
+    max_counter: u64,
+    next: Option<u64>,
+    issued: u64,
+    limit: Option<u64>,
+}
+
+impl IvSequence {
+    /// Sequence of 2^64 IVs counting up from zero.
+    pub fn new() -> IvSequence {
+        IvSequence {
+            prefix: 0,
+            max_counter: u64::MAX,
+            next: Some(0),
+            issued: 0,
+            limit: None,
+        }
+    }
+
+    /// Sequence of 2^32 IVs with `prefix` as the high 32 bits.
+    pub fn with_prefix(prefix: u32) -> IvSequence {
+        IvSequence {
+            prefix: (prefix as u64) << 32,
+            max_counter: u32::MAX as u64,
+            next: Some(0),
+            issued: 0,
+            limit: None,
+        }
+    }
+
+    /// Sequence of 2^32 IVs with a random prefix from `OsRng`.
+    ///
+    /// Panics if the operating system has no random source, like `OsRng`.
+    #[cfg(feature = "rand_core")]
+    pub fn with_random_prefix() -> IvSequence {
+        IvSequence::with_prefix(OsRng.next_u32())
+    }
+
+    /// Limits number of IVs (i.e. messages) issued by this sequence.
+    pub fn limit(mut self, max_messages: u64) -> IvSequence {
+        self.limit = Some(max_messages);
+        self
+    }
+
+    /// Number of IVs issued so far.
+    pub fn issued(&self) -> u64 {
+        self.issued
+    }
+
+    /// Issues next IV.
+    ///
+    /// Returns `Error::MessageLimitReached` if configured limit is reached and
+    /// `Error::IvSpaceExhausted` if every counter value was issued.
+    pub fn next_iv(&mut self) -> Result<InitVec, Error> {
+        if let Some(limit) = self.limit {
+            if self.issued >= limit {
+                return Err(Error::MessageLimitReached);
+            }
+        }
+        let counter = match self.next {
+            Some(counter) => counter,
+            None => return Err(Error::IvSpaceExhausted),
+        };
+        self.next = if counter == self.max_counter { None } else { Some(counter + 1) };
+        self.issued += 1;
+        Ok(InitVec::from(self.prefix | counter))
+    }
+}
+
+impl Default for IvSequence {
+    fn default() -> IvSequence {
+        IvSequence::new()
+    }
+}
+
+#[cfg(not(feature = "nostd"))]
+/// `Rabbit` wrapper that refuses to reuse an IV it has already seen.
+///
+/// There is no `reset`, so the only way to restart keystream is `reinit` with a fresh IV.
+/// Every used IV is remembered for the lifetime of the session, so memory grows with the
+/// number of messages; `limit` caps it.
+pub struct Session {
+    rabbit: Rabbit,
+    seen: HashSet<InitVec>,
+    limit: Option<u64>,
+}
+
+#[cfg(not(feature = "nostd"))]
+impl Session {
+    /// Setups `key` and initialization vector `iv`.
+    pub fn new(key: &Key, iv: &InitVec) -> Session {
+        let mut seen = HashSet::new();
+        seen.insert(iv.clone());
+        Session {
+            rabbit: Rabbit::new_iv(key, iv),
+            seen,
+            limit: None,
+        }
+    }
+
+    /// Limits number of IVs (i.e. messages) used by this session, the initial one
+    /// included.
+    pub fn limit(mut self, max_messages: u64) -> Session {
+        self.limit = Some(max_messages);
+        self
+    }
+
+    /// Returns `true` if `iv` was already used within this session.
+    pub fn is_used(&self, iv: &InitVec) -> bool {
+        self.seen.contains(iv)
+    }
+
+    /// Restores master state, than setups initialization vector `iv` on it.
+    ///
+    /// Returns `Error::IvReused` if `iv` was already used and `Error::MessageLimitReached`
+    /// if configured limit is reached, leaving state untouched.
+    pub fn reinit(&mut self, iv: &InitVec) -> Result<(), Error> {
+        if self.seen.contains(iv) {
+            return Err(Error::IvReused);
+        }
+        if let Some(limit) = self.limit {
+            if self.seen.len() as u64 >= limit {
+                return Err(Error::MessageLimitReached);
+            }
+        }
+        self.seen.insert(iv.clone());
+        self.rabbit.reinit(iv);
+        Ok(())
+    }
+
+    /// Takes next IV from `ivs` and reinits on it. Returns used IV.
+    pub fn reinit_next(&mut self, ivs: &mut IvSequence) -> Result<InitVec, Error> {
+        let iv = ivs.next_iv()?;
+        self.reinit(&iv)?;
+        Ok(iv)
+    }
+
+    /// Encrypts and writes bytes of `data` to `buf`.
+    /// Asserts that `buf.len() >= data.len()`.
+    pub fn encrypt(&mut self, data: &[u8], buf: &mut [u8]) {
+        self.rabbit.encrypt(data, buf)
+    }
+
+    #[inline]
+    /// Decrypts and writes bytes of `data` to `buf`.
+    /// Asserts that `buf.len() >= data.len()`.
+    pub fn decrypt(&mut self, data: &[u8], buf: &mut [u8]) {
+        self.rabbit.decrypt(data, buf)
+    }
+
+    /// Encrypts bytes of `data` inplace.
+    pub fn encrypt_inplace(&mut self, data: &mut [u8]) {
+        self.rabbit.encrypt_inplace(data)
+    }
+
+    #[inline]
+    /// Decrypts bytes of `data` inplace.
+    pub fn decrypt_inplace(&mut self, data: &mut [u8]) {
+        self.rabbit.decrypt_inplace(data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::IvSequence;
+    #[cfg(not(feature = "nostd"))]
+    use super::Session;
+    use super::super::{Error, InitVec};
+    #[cfg(not(feature = "nostd"))]
+    use super::super::{Key, Rabbit};
+
+    #[test]
+    fn counts_up_from_zero() {
+        let mut ivs = IvSequence::new();
+        assert_eq!(ivs.next_iv(), Ok(InitVec::from(0)));
+        assert_eq!(ivs.next_iv(), Ok(InitVec::from(1)));
+        assert_eq!(ivs.next_iv(), Ok(InitVec::from(2)));
+        assert_eq!(ivs.issued(), 3);
+    }
+
+    #[test]
+    fn prefix_occupies_high_bits() {
+        let mut ivs = IvSequence::with_prefix(0xDEADBEEF);
+        assert_eq!(ivs.next_iv(), Ok(InitVec([0, 0, 0, 0, 0xEF, 0xBE, 0xAD, 0xDE])));
+        assert_eq!(ivs.next_iv(), Ok(InitVec([1, 0, 0, 0, 0xEF, 0xBE, 0xAD, 0xDE])));
+    }
+
+    #[test]
+    fn message_limit() {
+        let mut ivs = IvSequence::new().limit(2);
+        assert!(ivs.next_iv().is_ok());
+        assert!(ivs.next_iv().is_ok());
+        assert_eq!(ivs.next_iv(), Err(Error::MessageLimitReached));
+        assert_eq!(ivs.issued(), 2);
+    }
+
+    #[test]
+    fn counter_space_exhaustion() {
+        let mut ivs = IvSequence::with_prefix(7);
+        ivs.next = Some(u32::MAX as u64 - 1);
+        assert_eq!(ivs.next_iv(), Ok(InitVec::from(0x0000_0007_FFFF_FFFE)));
+        assert_eq!(ivs.next_iv(), Ok(InitVec::from(0x0000_0007_FFFF_FFFF)));
+        assert_eq!(ivs.next_iv(), Err(Error::IvSpaceExhausted));
+        assert_eq!(ivs.next_iv(), Err(Error::IvSpaceExhausted));
+
+        let mut ivs = IvSequence::new();
+        ivs.next = Some(u64::MAX);
+        assert_eq!(ivs.next_iv(), Ok(InitVec::from(u64::MAX)));
+        assert_eq!(ivs.next_iv(), Err(Error::IvSpaceExhausted));
+    }
+
+    #[cfg(not(feature = "nostd"))]
+    #[test]
+    fn session_refuses_reused_iv() {
+        let key = Key::from([0x42; 16]);
+        let mut session = Session::new(&key, &InitVec::from(1));
+        assert_eq!(session.reinit(&InitVec::from(1)), Err(Error::IvReused));
+        assert_eq!(session.reinit(&InitVec::from(2)), Ok(()));
+        assert_eq!(session.reinit(&InitVec::from(2)), Err(Error::IvReused));
+        assert!(session.is_used(&InitVec::from(1)));
+        assert!(!session.is_used(&InitVec::from(3)));
+    }
+
+    #[cfg(feature = "rand_core")]
+    #[test]
+    fn random_prefix() {
+        let mut first = IvSequence::with_random_prefix();
+        let mut second = IvSequence::with_random_prefix();
+        // Equal prefixes have probability 2^-32.
+        assert_ne!(first.next_iv(), second.next_iv());
+        let iv = u64::from_le_bytes(*first.next_iv().unwrap());
+        assert_eq!(iv & 0xFFFF_FFFF, 1);
+    }
+
+    #[cfg(not(feature = "nostd"))]
+    #[test]
+    fn session_limit() {
+        let key = Key::from([0x42; 16]);
+        let mut session = Session::new(&key, &InitVec::from(1)).limit(2);
+        assert_eq!(session.reinit(&InitVec::from(2)), Ok(()));
+        assert_eq!(session.reinit(&InitVec::from(3)), Err(Error::MessageLimitReached));
+        assert_eq!(session.reinit(&InitVec::from(1)), Err(Error::IvReused));
+        assert!(!session.is_used(&InitVec::from(3)));
+    }
+
+    #[cfg(not(feature = "nostd"))]
+    #[test]
+    fn session_keystream_matches_rabbit() {
+        let key = Key::from([0x42; 16]);
+        let mut ivs = IvSequence::new();
+        let mut session = Session::new(&key, &ivs.next_iv().unwrap());
+        let iv = session.reinit_next(&mut ivs).unwrap();
+        assert_eq!(iv, InitVec::from(1));
+
+        let mut expected = [0; 32];
+        Rabbit::new_iv(&key, &iv).encrypt_inplace(&mut expected);
+        let mut actual = [0; 32];
+        session.encrypt_inplace(&mut actual);
+        assert_eq!(expected, actual);
+
+        let mut ivs = IvSequence::new();
+        assert_eq!(session.reinit_next(&mut ivs), Err(Error::IvReused));
+    }
+}
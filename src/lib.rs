@@ -1,4 +1,3 @@
-#![cfg_attr(feature = "nostd", feature(no_std, core_slice_ext))]
 #![cfg_attr(feature = "nostd", no_std)]
 
 #[cfg(not(feature = "nostd"))]
@@ -6,12 +5,75 @@ use std::io;
 #[cfg(not(feature = "nostd"))]
 use std::fmt;
 #[cfg(not(feature = "nostd"))]
+use std::hash::{Hash, Hasher};
+#[cfg(not(feature = "nostd"))]
 use std::ops::Deref;
 
+#[cfg(feature = "nostd")]
+use core::hash::{Hash, Hasher};
 #[cfg(feature = "nostd")]
 use core::ops::Deref;
 
-const WORDSIZE: u64 = (1 << 32);
+#[cfg(all(feature = "nostd", feature = "alloc"))]
+extern crate alloc;
+#[cfg(all(feature = "nostd", feature = "alloc"))]
+use alloc::vec::Vec;
+
+#[cfg(feature = "base64")]
+extern crate base64;
+#[cfg(feature = "bytes")]
+extern crate bytes;
+#[cfg(feature = "hkdf")]
+extern crate hkdf;
+#[cfg(feature = "hmac")]
+extern crate hmac;
+#[cfg(feature = "md-5")]
+extern crate md5;
+#[cfg(feature = "sha2")]
+extern crate sha2;
+#[cfg(feature = "rand_core")]
+extern crate rand_core;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "tokio-util")]
+extern crate tokio_util;
+#[cfg(feature = "wasm-bindgen")]
+extern crate wasm_bindgen;
+#[cfg(feature = "x25519-dalek")]
+extern crate x25519_dalek;
+
+#[cfg(all(feature = "channel", not(feature = "nostd")))]
+pub mod channel;
+#[cfg(all(feature = "codec", not(feature = "nostd")))]
+pub mod codec;
+#[cfg(all(feature = "container", not(feature = "nostd")))]
+pub mod container;
+#[cfg(all(feature = "cryptojs", not(feature = "nostd")))]
+pub mod cryptojs;
+#[cfg(feature = "cryptopp")]
+pub mod cryptopp;
+mod error;
+mod iv;
+mod limit;
+#[cfg(all(feature = "rayon", not(feature = "nostd")))]
+pub mod parallel;
+#[cfg(all(test, not(feature = "nostd")))]
+mod timing;
+mod word;
+#[cfg(all(test, feature = "reference-c", not(feature = "nostd"), not(target_arch = "wasm32")))]
+mod reference;
+#[cfg(all(test, not(feature = "nostd")))]
+mod vectors;
+#[cfg(all(feature = "wasm", not(feature = "nostd")))]
+pub mod wasm;
+
+pub use error::Error;
+pub use iv::IvSequence;
+pub use limit::{Limit, Limited, OnLimit};
+#[cfg(not(feature = "nostd"))]
+pub use iv::Session;
+
+const WORDSIZE: u64 = 1 << 32;
 const A: [u32; 8] = [0x4D34D34D, 0xD34D34D3, 0x34D34D34, 0x4D34D34D,
                      0xD34D34D3, 0x34D34D34, 0x4D34D34D, 0xD34D34D3];
 
@@ -23,9 +85,34 @@ const A: [u32; 8] = [0x4D34D34D, 0xD34D34D3, 0x34D34D34, 0x4D34D34D,
 /// let key2: Key = byte_slice_with_len_eq_16.into();
 /// let key3: Key = byte_slice_with_len_ne_16.into(); // Panic!
 /// ```
-#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+///
+/// Keys are compared in constant time.
+#[derive(Clone, Eq, Debug)]
 pub struct Key([u8; 16]);
 
+impl Key {
+    /// Same as `From<[u8; 16]>`, usable in constants.
+    pub const fn new(key: [u8; 16]) -> Key {
+        Key(key)
+    }
+}
+
+impl PartialEq for Key {
+    fn eq(&self, other: &Key) -> bool {
+        let mut diff = 0;
+        for (a, b) in self.0.iter().zip(other.0.iter()) {
+            diff |= a ^ b;
+        }
+        diff == 0
+    }
+}
+
+impl Hash for Key {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
 impl Deref for Key {
     type Target = [u8; 16];
     fn deref(&self) -> &[u8; 16] {
@@ -40,13 +127,11 @@ impl From<[u8; 16]> for Key {
 }
 
 /// Asserts that slice.len() == 16
-impl<'a> From<&'a [u8]> for Key {
+impl From<&[u8]> for Key {
     fn from(slice: &[u8]) -> Key {
         assert_eq!(slice.len(), 16);
         let mut key = [0; 16];
-        for i in 0..16 {
-            key[i] = slice[i];
-        }
+        key.copy_from_slice(slice);
         Key(key)
     }
 }
@@ -62,6 +147,13 @@ impl<'a> From<&'a [u8]> for Key {
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct InitVec([u8; 8]);
 
+impl InitVec {
+    /// Same as `From<[u8; 8]>`, usable in constants.
+    pub const fn new(iv: [u8; 8]) -> InitVec {
+        InitVec(iv)
+    }
+}
+
 impl Deref for InitVec {
     type Target = [u8; 8];
     fn deref(&self) -> &[u8; 8] {
@@ -72,8 +164,8 @@ impl Deref for InitVec {
 impl From<u64> for InitVec {
     fn from(v: u64) -> InitVec {
         let mut iv = [0u8; 8];
-        for i in 0..8 {
-            iv[i] = (v >> (i * 8)) as u8;
+        for (i, byte) in iv.iter_mut().enumerate() {
+            *byte = (v >> (i * 8)) as u8;
         }
         InitVec(iv)
     }
@@ -85,13 +177,11 @@ impl From<[u8; 8]> for InitVec {
     }
 }
 
-impl<'a> From<&'a [u8]> for InitVec {
-    fn from(slice: &'a [u8]) -> InitVec {
+impl From<&[u8]> for InitVec {
+    fn from(slice: &[u8]) -> InitVec {
         assert_eq!(slice.len(), 8);
         let mut iv = [0; 8];
-        for i in 0..8 {
-            iv[i] = slice[i];
-        }
+        iv.copy_from_slice(slice);
         InitVec(iv)
     }
 }
@@ -107,23 +197,23 @@ struct State {
 impl fmt::Display for State {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if self.carry_bit > 0 {
-            try!(write!(f, "Carry bit: SET\n"));
+            writeln!(f, "Carry bit: SET")?;
         } else {
-            try!(write!(f, "Carry bit: UNSET\n"));
+            writeln!(f, "Carry bit: UNSET")?;
         }
         for i in 0..8 {
-            try!(write!(f, "X{} = 0x{:08X}, ", i, self.state_vars[i]));
+            write!(f, "X{} = 0x{:08X}, ", i, self.state_vars[i])?;
             if i == 3 || i == 7 {
-                try!(write!(f, "\n"));
+                writeln!(f)?;
             }
         }
         for i in 0..8 {
-            try!(write!(f, "C{} = 0x{:08X}, ", i, self.counter_vars[i]));
+            write!(f, "C{} = 0x{:08X}, ", i, self.counter_vars[i])?;
             if i == 3 || i == 7 {
-                try!(write!(f, "\n"));
+                writeln!(f)?;
             }
         }
-        return Ok(());
+        Ok(())
     }
 }
 
@@ -154,7 +244,7 @@ fn setup_key(state: &mut State, key: &Key) {
     }
 
     for j in 0..8 {
-        state.counter_vars[j] = state.counter_vars[j] ^ state.state_vars[(j + 4) % 8];
+        state.counter_vars[j] ^= state.state_vars[(j + 4) % 8];
     }
 }
 
@@ -164,37 +254,63 @@ fn setup_iv(state: &mut State, iv: &InitVec) {
     let i1 = (i0 >> 16) | (i2 & 0xFFFF0000);
     let i3 = (i2 << 16) | (i0 & 0x0000FFFF);
 
-    state.counter_vars[0] = state.counter_vars[0] ^ i0;
-    state.counter_vars[1] = state.counter_vars[1] ^ i1;
-    state.counter_vars[2] = state.counter_vars[2] ^ i2;
-    state.counter_vars[3] = state.counter_vars[3] ^ i3;
-    state.counter_vars[4] = state.counter_vars[4] ^ i0;
-    state.counter_vars[5] = state.counter_vars[5] ^ i1;
-    state.counter_vars[6] = state.counter_vars[6] ^ i2;
-    state.counter_vars[7] = state.counter_vars[7] ^ i3;
+    state.counter_vars[0] ^= i0;
+    state.counter_vars[1] ^= i1;
+    state.counter_vars[2] ^= i2;
+    state.counter_vars[3] ^= i3;
+    state.counter_vars[4] ^= i0;
+    state.counter_vars[5] ^= i1;
+    state.counter_vars[6] ^= i2;
+    state.counter_vars[7] ^= i3;
 
     for _ in 0..4 {
         next_state(state);
     }
 }
 
+/// Whether `word` core is used instead of `u64` arithmetic below: on targets without
+/// 64-bit pointers or with `word-core` feature.
+const WORD_CORE: bool = cfg!(any(feature = "word-core", not(target_pointer_width = "64")));
+
 fn counter_update(state: &mut State) {
-    for j in 0..8 {
-        let temp = state.counter_vars[j] as u64 + A[j] as u64 + state.carry_bit as u64;
+    if WORD_CORE {
+        word::counter_update(state)
+    } else {
+        counter_update_wide(state)
+    }
+}
+
+fn counter_update_wide(state: &mut State) {
+    for (counter, a) in state.counter_vars.iter_mut().zip(A.iter()) {
+        let temp = *counter as u64 + *a as u64 + state.carry_bit as u64;
         state.carry_bit = ((temp / WORDSIZE) as u8) & 0b1;
-        state.counter_vars[j] = (temp % WORDSIZE) as u32;
+        *counter = (temp % WORDSIZE) as u32;
     }
 }
 
+#[inline]
+fn g_func(u: u32, v: u32) -> u32 {
+    if WORD_CORE {
+        word::g_func(u, v)
+    } else {
+        g_func_wide(u, v)
+    }
+}
+
+#[inline]
+fn g_func_wide(u: u32, v: u32) -> u32 {
+    let u_plus_v = u as u64 + v as u64;
+    let square_uv = (u_plus_v % WORDSIZE) * (u_plus_v % WORDSIZE);
+    (square_uv ^ (square_uv >> 32)) as u32
+}
+
 fn next_state(state: &mut State) {
     let mut g = [0u32; 8];
 
     counter_update(state);
 
-    for j in 0..8 {
-        let u_plus_v = state.state_vars[j] as u64 + state.counter_vars[j] as u64;
-        let square_uv = (u_plus_v % WORDSIZE) * (u_plus_v % WORDSIZE);
-        g[j] = (square_uv ^ (square_uv >> 32)) as u32;
+    for (j, g) in g.iter_mut().enumerate() {
+        *g = g_func(state.state_vars[j], state.counter_vars[j]);
     }
 
     state.state_vars[0] = g[0].wrapping_add(g[7].rotate_left(16))
@@ -240,62 +356,130 @@ fn extract(state: &State) -> [u8; 16] {
     s[0xE] = s127_112 as u8;
     s[0xF] = (s127_112 >> 8) as u8;
 
-    return s;
+    s
+}
+
+/// Precomputed key setup, shareable between threads.
+///
+/// Spawned ciphers copy the key setup state once: they keep it for `Rabbit::reinit` and
+/// `Rabbit::reset_key_only` next to the running state.
+///
+/// ```ignore
+/// let key = Arc::new(RabbitKey::new(&key));
+/// // In each thread:
+/// let mut rabbit = key.rabbit_iv(&iv);
+/// ```
+#[derive(Clone)]
+pub struct RabbitKey {
+    master_state: State,
+}
+
+impl RabbitKey {
+    /// Setupы given `key` on an empty rabbit state.
+    pub fn new(key: &Key) -> RabbitKey {
+        let mut master_state = State::default();
+        setup_key(&mut master_state, key);
+        RabbitKey {
+            master_state,
+        }
+    }
+
+    /// Cipher in `Mode::KeyOnly`, same as `Rabbit::new`.
+    pub fn rabbit(&self) -> Rabbit {
+        Rabbit::from_master(self.master_state.clone(), self.master_state.clone(), None)
+    }
+
+    /// Cipher in `Mode::WithIv`, same as `Rabbit::new_iv`.
+    pub fn rabbit_iv(&self, iv: &InitVec) -> Rabbit {
+        let mut state = self.master_state.clone();
+        setup_iv(&mut state, iv);
+        Rabbit::from_master(self.master_state.clone(), state, Some(iv.clone()))
+    }
+}
+
+/// Which setup the keystream of a `Rabbit` starts from.
+///
+/// Same key gives different keystreams in each mode.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Mode {
+    /// Key setup only, `Rabbit::new` or `Rabbit::reset_key_only`.
+    KeyOnly,
+    /// Key and IV setup, `Rabbit::new_iv` or `Rabbit::reinit`.
+    WithIv,
 }
 
 pub struct Rabbit {
+    /// State after key setup.
     master_state: State,
     state: State,
+    /// IV of `Mode::WithIv`, `reset` repeats its setup instead of keeping one more state.
+    iv: Option<InitVec>,
     buf: [u8; 16],
     buf_idx: u8,
+    blocks: u64,
 }
 
 impl Rabbit {
     /// Setupы given `key` on an empty rabbit state.
     pub fn new(key: &Key) -> Rabbit {
-        let mut state = Default::default();
-        setup_key(&mut state, key);
-        Rabbit {
-            master_state: state.clone(),
-            state: state,
-            buf: [0; 16],
-            buf_idx: 0x10,
-        }
+        RabbitKey::new(key).rabbit()
     }
 
     /// Setupы given `key` on an empty rabbit state, then setupы initialization vector `iv` on it.
     pub fn new_iv(key: &Key, iv: &InitVec) -> Rabbit {
-        let mut state = Default::default();
-        setup_key(&mut state, key);
-        let master_state = state.clone();
-        setup_iv(&mut state, iv);
+        RabbitKey::new(key).rabbit_iv(iv)
+    }
+
+    fn from_master(master_state: State, state: State, iv: Option<InitVec>) -> Rabbit {
         Rabbit {
-            master_state: master_state,
-            state: state,
+            master_state,
+            state,
+            iv,
             buf: [0; 16],
             buf_idx: 0x10,
+            blocks: 0,
+        }
+    }
+
+    /// Mode of current keystream.
+    pub fn mode(&self) -> Mode {
+        match self.iv {
+            Some(_) => Mode::WithIv,
+            None => Mode::KeyOnly,
         }
     }
 
-    /// Restores master state.
+    /// Restarts current keystream: restores state after key setup in `Mode::KeyOnly`, or
+    /// after the last IV setup in `Mode::WithIv`.
     pub fn reset(&mut self) {
         self.state = self.master_state.clone();
+        if let Some(ref iv) = self.iv {
+            setup_iv(&mut self.state, iv);
+        }
         self.buf_idx = 0x10;
+        self.blocks = 0;
+    }
+
+    /// Restores master state (switching to `Mode::KeyOnly`), i.e. restarts keystream of
+    /// `Rabbit::new`.
+    pub fn reset_key_only(&mut self) {
+        self.iv = None;
+        self.reset();
     }
 
-    /// Restores master state, than setups initialization vector `iv` on it.
+    /// Restores master state, than setups initialization vector `iv` on it (switching to
+    /// `Mode::WithIv`).
     pub fn reinit(&mut self, iv: &InitVec) {
-        self.state = self.master_state.clone();
-        self.buf_idx = 0x10;
-        setup_iv(&mut self.state, iv);
+        self.iv = Some(iv.clone());
+        self.reset();
     }
 
     /// Encrypts and writes bytes of `data` to `buf`.
     /// Asserts that `buf.len() >= data.len()`.
     pub fn encrypt(&mut self, data: &[u8], buf: &mut [u8]) {
         assert!(buf.len() >= data.len());
-        for i in 0..data.len() {
-            buf[i] = data[i] ^ self.get_s_byte();
+        for (out, byte) in buf.iter_mut().zip(data.iter()) {
+            *out = byte ^ self.get_s_byte();
         }
     }
 
@@ -308,8 +492,8 @@ impl Rabbit {
 
     /// Encrypts bytes of `data` inplace.
     pub fn encrypt_inplace(&mut self, data: &mut [u8]) {
-        for i in 0..data.len() {
-            data[i] ^= self.get_s_byte();
+        for byte in data.iter_mut() {
+            *byte ^= self.get_s_byte();
         }
     }
 
@@ -319,13 +503,91 @@ impl Rabbit {
         self.encrypt_inplace(data)
     }
 
+    /// Like `encrypt`, but returns `Error::LengthMismatch` instead of panicking if
+    /// `buf.len() < data.len()`. No keystream is consumed on error.
+    pub fn try_encrypt(&mut self, data: &[u8], buf: &mut [u8]) -> Result<(), Error> {
+        if buf.len() < data.len() {
+            return Err(Error::LengthMismatch { data: data.len(), buf: buf.len() });
+        }
+        self.encrypt(data, buf);
+        Ok(())
+    }
+
+    #[inline]
+    /// Like `decrypt`, but returns `Error::LengthMismatch` instead of panicking if
+    /// `buf.len() < data.len()`. No keystream is consumed on error.
+    pub fn try_decrypt(&mut self, data: &[u8], buf: &mut [u8]) -> Result<(), Error> {
+        self.try_encrypt(data, buf)
+    }
+
+    /// Encrypts `data` into `buf` of exactly the same length (buffer-to-buffer, as
+    /// `apply_keystream_b2b` of RustCrypto). Returns `Error::LengthMismatch` otherwise.
+    pub fn encrypt_b2b(&mut self, data: &[u8], buf: &mut [u8]) -> Result<(), Error> {
+        if buf.len() != data.len() {
+            return Err(Error::LengthMismatch { data: data.len(), buf: buf.len() });
+        }
+        self.encrypt(data, buf);
+        Ok(())
+    }
+
+    #[inline]
+    /// Decrypts `data` into `buf` of exactly the same length.
+    /// Returns `Error::LengthMismatch` otherwise.
+    pub fn decrypt_b2b(&mut self, data: &[u8], buf: &mut [u8]) -> Result<(), Error> {
+        self.encrypt_b2b(data, buf)
+    }
+
+    #[cfg(any(not(feature = "nostd"), feature = "alloc"))]
+    /// Encrypts `data` into newly allocated vector.
+    pub fn encrypt_to_vec(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut vec = data.to_vec();
+        self.encrypt_inplace(&mut vec);
+        vec
+    }
+
+    #[cfg(any(not(feature = "nostd"), feature = "alloc"))]
+    #[inline]
+    /// Decrypts `data` into newly allocated vector.
+    pub fn decrypt_to_vec(&mut self, data: &[u8]) -> Vec<u8> {
+        self.encrypt_to_vec(data)
+    }
+
+    /// Discards next `count` bytes of keystream, i.e. seeks forward by `count` bytes.
+    ///
+    /// Whole blocks are skipped without extracting keystream.
+    pub fn skip(&mut self, count: u64) {
+        let mut count = count;
+        while count > 0 && self.buf_idx < 0x10 {
+            self.buf_idx += 1;
+            count -= 1;
+        }
+        for _ in 0..count / 16 {
+            next_state(&mut self.state);
+        }
+        self.blocks += count / 16;
+        for _ in 0..count % 16 {
+            self.get_s_byte();
+        }
+    }
+
+    /// Number of 128-bit keystream blocks generated since last key or IV setup.
+    pub fn block_counter(&self) -> u64 {
+        self.blocks
+    }
+
+    /// Number of keystream bytes consumed since last key or IV setup.
+    fn position(&self) -> u64 {
+        self.blocks * 16 - (0x10 - self.buf_idx as u64)
+    }
+
     #[inline]
     fn get_s_byte(&mut self) -> u8 {
         if self.buf_idx == 0x10 {
             next_state(&mut self.state);
-            let s = extract(&mut self.state);
+            let s = extract(&self.state);
             self.buf = s;
             self.buf_idx = 0;
+            self.blocks += 1;
         }
         let byte = self.buf[self.buf_idx as usize];
         self.buf_idx += 1;
@@ -344,8 +606,8 @@ pub struct Stream<S> {
 impl<S> Stream<S> {
     pub fn new(rabbit: Rabbit, stream: S) -> Stream<S> {
         Stream {
-            stream: stream,
-            rabbit: rabbit,
+            stream,
+            rabbit,
         }
     }
 
@@ -359,7 +621,7 @@ impl<S> Stream<S> {
 #[cfg(not(feature = "nostd"))]
 impl<S: io::Read> io::Read for Stream<S> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        let count = try!(self.stream.read(buf));
+        let count = self.stream.read(buf)?;
         self.rabbit.encrypt_inplace(&mut buf[0..count]);
         Ok(count)
     }
@@ -370,7 +632,7 @@ impl<S: io::Write> io::Write for Stream<S> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         let mut vec = vec![0; buf.len()];
         self.rabbit.encrypt(buf, &mut vec[..]);
-        try!(self.stream.write_all(&vec[..]));
+        self.stream.write_all(&vec[..])?;
         Ok(buf.len())
     }
 
@@ -387,8 +649,10 @@ mod test {
     use std::io::Write;
 
     use super::{
+        Error,
         Key,
         InitVec,
+        Mode,
         Rabbit,
         extract,
         next_state,
@@ -642,4 +906,152 @@ mod test {
         S[1] = [0x96,0xC8,0xF2,0x79,0x47,0xF4,0x2C,0x5B,0xAE,0xAE,0x67,0xC6,0xAC,0xC3,0x5B,0x03]
         S[2] = [0x9F,0xCB,0xFC,0x89,0x5F,0xA7,0x1C,0x17,0x31,0x3D,0xF0,0x34,0xF0,0x15,0x51,0xCB]
     }
+
+    #[test]
+    fn try_encrypt_checks_length() {
+        let key = Key::from([0x11; 16]);
+        let mut expected = [0u8; 20];
+        Rabbit::new(&key).encrypt_inplace(&mut expected);
+
+        let mut rabbit = Rabbit::new(&key);
+        let mut short = [0u8; 19];
+        assert_eq!(rabbit.try_encrypt(&[0; 20], &mut short),
+                   Err(Error::LengthMismatch { data: 20, buf: 19 }));
+        // Failed call consumes no keystream, longer buffer keeps its tail.
+        let mut buf = [0xFFu8; 24];
+        rabbit.try_encrypt(&[0; 20], &mut buf).unwrap();
+        assert_eq!(&buf[..20], &expected[..]);
+        assert_eq!(&buf[20..], &[0xFF; 4]);
+
+        let mut rabbit = Rabbit::new(&key);
+        let mut plain = [0u8; 20];
+        rabbit.try_decrypt(&expected, &mut plain).unwrap();
+        assert_eq!(plain, [0; 20]);
+    }
+
+    #[test]
+    fn b2b_requires_equal_length() {
+        let key = Key::from([0x22; 16]);
+        let mut expected = [0u8; 20];
+        Rabbit::new(&key).encrypt_inplace(&mut expected);
+
+        let mut rabbit = Rabbit::new(&key);
+        assert_eq!(rabbit.encrypt_b2b(&[0; 20], &mut [0; 21]),
+                   Err(Error::LengthMismatch { data: 20, buf: 21 }));
+        assert_eq!(rabbit.encrypt_b2b(&[0; 20], &mut [0; 19]),
+                   Err(Error::LengthMismatch { data: 20, buf: 19 }));
+        let mut buf = [0u8; 20];
+        rabbit.encrypt_b2b(&[0; 20], &mut buf).unwrap();
+        assert_eq!(buf, expected);
+
+        let mut rabbit = Rabbit::new(&key);
+        rabbit.decrypt_b2b(&expected, &mut buf).unwrap();
+        assert_eq!(buf, [0; 20]);
+    }
+
+    #[cfg(any(not(feature = "nostd"), feature = "alloc"))]
+    #[test]
+    fn to_vec() {
+        let key = Key::from([0x33; 16]);
+        let iv = InitVec::from(7);
+        let mut expected = [0x5Au8; 40];
+        Rabbit::new_iv(&key, &iv).encrypt_inplace(&mut expected);
+
+        let encrypted = Rabbit::new_iv(&key, &iv).encrypt_to_vec(&[0x5A; 40]);
+        assert_eq!(&encrypted[..], &expected[..]);
+        assert_eq!(&Rabbit::new_iv(&key, &iv).decrypt_to_vec(&encrypted)[..], &[0x5A; 40][..]);
+    }
+
+    #[test]
+    fn const_constructors() {
+        const KEY: Key = Key::new([0x66; 16]);
+        const IV: InitVec = InitVec::new([1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(KEY, Key::from([0x66; 16]));
+        assert_eq!(IV, InitVec::from(0x0807060504030201));
+    }
+
+    #[test]
+    fn reset_restarts_current_mode() {
+        let key = Key::from([0x44; 16]);
+        let iv = InitVec::from(0x0102030405060708);
+        let mut key_only = [0u8; 40];
+        Rabbit::new(&key).encrypt_inplace(&mut key_only);
+        let mut with_iv = [0u8; 40];
+        Rabbit::new_iv(&key, &iv).encrypt_inplace(&mut with_iv);
+        assert_ne!(key_only, with_iv);
+
+        let keystream = |rabbit: &mut Rabbit| {
+            let mut d = [0u8; 40];
+            rabbit.encrypt_inplace(&mut d);
+            d
+        };
+
+        let mut rabbit = Rabbit::new_iv(&key, &iv);
+        assert_eq!(rabbit.mode(), Mode::WithIv);
+        keystream(&mut rabbit);
+        rabbit.reset();
+        assert_eq!(keystream(&mut rabbit), with_iv);
+
+        let mut rabbit = Rabbit::new(&key);
+        assert_eq!(rabbit.mode(), Mode::KeyOnly);
+        assert_eq!(keystream(&mut rabbit), key_only);
+        rabbit.reinit(&iv);
+        assert_eq!(rabbit.mode(), Mode::WithIv);
+        assert_eq!(keystream(&mut rabbit), with_iv);
+        rabbit.reset();
+        assert_eq!(keystream(&mut rabbit), with_iv);
+
+        rabbit.reset_key_only();
+        assert_eq!(rabbit.mode(), Mode::KeyOnly);
+        assert_eq!(keystream(&mut rabbit), key_only);
+        rabbit.reset();
+        assert_eq!(keystream(&mut rabbit), key_only);
+
+        // IV setup always starts from key setup, not from a previous IV.
+        let mut rabbit = Rabbit::new_iv(&key, &InitVec::from(99));
+        rabbit.reinit(&iv);
+        assert_eq!(keystream(&mut rabbit), with_iv);
+        assert_eq!(rabbit.block_counter(), 3);
+    }
+
+    #[cfg(not(feature = "nostd"))]
+    #[test]
+    fn rabbit_key_spawns_ciphers_across_threads() {
+        use super::RabbitKey;
+
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<RabbitKey>();
+
+        let key = Key::from([0x55; 16]);
+        let rabbit_key = RabbitKey::new(&key);
+        let mut key_only = [0u8; 40];
+        Rabbit::new(&key).encrypt_inplace(&mut key_only);
+        let mut d = [0u8; 40];
+        let mut rabbit = rabbit_key.rabbit();
+        assert_eq!(rabbit.mode(), Mode::KeyOnly);
+        rabbit.encrypt_inplace(&mut d);
+        assert_eq!(d, key_only);
+
+        ::std::thread::scope(|scope| {
+            for i in 0..4u64 {
+                let rabbit_key = &rabbit_key;
+                let key = &key;
+                scope.spawn(move || {
+                    let iv = InitVec::from(i);
+                    let mut expected = [0u8; 40];
+                    Rabbit::new_iv(key, &iv).encrypt_inplace(&mut expected);
+                    let mut rabbit = rabbit_key.rabbit_iv(&iv);
+                    assert_eq!(rabbit.mode(), Mode::WithIv);
+                    let mut d = [0u8; 40];
+                    rabbit.encrypt_inplace(&mut d);
+                    assert_eq!(d, expected);
+                    // Spawned cipher keeps key setup for `reinit` and `reset_key_only`.
+                    rabbit.reset_key_only();
+                    let mut d = [0u8; 40];
+                    rabbit.encrypt_inplace(&mut d);
+                    assert_eq!(d, key_only);
+                });
+            }
+        });
+    }
 }
@@ -0,0 +1,215 @@
+use super::{extract, next_state, Error, Key, Rabbit};
+
+/// Amount of keystream allowed per key/IV.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Limit {
+    /// Limit in bytes.
+    Bytes(u64),
+    /// Limit in 128-bit blocks.
+    Blocks(u64),
+}
+
+impl Limit {
+    fn bytes(&self) -> u64 {
+        match *self {
+            Limit::Bytes(bytes) => bytes,
+            Limit::Blocks(blocks) => blocks.saturating_mul(16),
+        }
+    }
+}
+
+/// Behavior of `Limited` once the limit is reached.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum OnLimit {
+    /// Refuse to process data past the limit with `Error::KeystreamLimitReached`.
+    Error,
+    /// Derive next key from keystream and continue with it.
+    ///
+    /// Next key is the next full keystream block (not used for data) and keystream
+    /// continues as of `Rabbit::new(&next_key)`, so both peers ratchet in lockstep.
+    Rekey,
+}
+
+/// `Rabbit` wrapper that enforces a keystream usage limit.
+///
+/// ```ignore
+/// let mut rabbit = Limited::new(Rabbit::new_iv(&key, &iv), Limit::Bytes(1 << 30), OnLimit::Rekey);
+/// rabbit.encrypt_inplace(&mut data)?;
+/// ```
+pub struct Limited {
+    rabbit: Rabbit,
+    limit: u64,
+    on_limit: OnLimit,
+    rekeys: u64,
+}
+
+impl Limited {
+    /// Wraps `rabbit`. Keystream already consumed by `rabbit` counts against the limit.
+    pub fn new(rabbit: Rabbit, limit: Limit, on_limit: OnLimit) -> Limited {
+        Limited {
+            rabbit,
+            limit: limit.bytes(),
+            on_limit,
+            rekeys: 0,
+        }
+    }
+
+    /// Number of keystream bytes left before the limit for the current key.
+    pub fn remaining(&self) -> u64 {
+        self.limit.saturating_sub(self.rabbit.position())
+    }
+
+    /// Number of 128-bit keystream blocks generated with the current key.
+    pub fn block_counter(&self) -> u64 {
+        self.rabbit.block_counter()
+    }
+
+    /// Number of rekeys performed so far.
+    pub fn rekeys(&self) -> u64 {
+        self.rekeys
+    }
+
+    /// Returns wrapped cipher.
+    pub fn into_inner(self) -> Rabbit {
+        self.rabbit
+    }
+
+    /// Encrypts and writes bytes of `data` to `buf`.
+    /// Asserts that `buf.len() >= data.len()`.
+    ///
+    /// With `OnLimit::Error` nothing is written if `data` doesn't fit into the limit.
+    pub fn encrypt(&mut self, data: &[u8], buf: &mut [u8]) -> Result<(), Error> {
+        assert!(buf.len() >= data.len());
+        let buf = &mut buf[..data.len()];
+        buf.copy_from_slice(data);
+        self.encrypt_inplace(buf)
+    }
+
+    #[inline]
+    /// Decrypts and writes bytes of `data` to `buf`.
+    /// Asserts that `buf.len() >= data.len()`.
+    pub fn decrypt(&mut self, data: &[u8], buf: &mut [u8]) -> Result<(), Error> {
+        self.encrypt(data, buf)
+    }
+
+    /// Encrypts bytes of `data` inplace.
+    ///
+    /// With `OnLimit::Error` `data` is untouched if it doesn't fit into the limit.
+    pub fn encrypt_inplace(&mut self, data: &mut [u8]) -> Result<(), Error> {
+        if self.on_limit == OnLimit::Error && data.len() as u64 > self.remaining() {
+            return Err(Error::KeystreamLimitReached);
+        }
+        let mut data = data;
+        while !data.is_empty() {
+            if self.remaining() == 0 {
+                if self.limit == 0 {
+                    return Err(Error::KeystreamLimitReached);
+                }
+                self.rekey();
+            }
+            let len = if (data.len() as u64) < self.remaining() {
+                data.len()
+            } else {
+                self.remaining() as usize
+            };
+            let (head, tail) = data.split_at_mut(len);
+            self.rabbit.encrypt_inplace(head);
+            data = tail;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    /// Decrypts bytes of `data` inplace.
+    pub fn decrypt_inplace(&mut self, data: &mut [u8]) -> Result<(), Error> {
+        self.encrypt_inplace(data)
+    }
+
+    fn rekey(&mut self) {
+        next_state(&mut self.rabbit.state);
+        let key = Key(extract(&self.rabbit.state));
+        self.rabbit = Rabbit::new(&key);
+        self.rekeys += 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Limit, Limited, OnLimit};
+    use super::super::{Error, InitVec, Key, Rabbit};
+
+    fn rabbit() -> Rabbit {
+        Rabbit::new_iv(&Key::from([0x11; 16]), &InitVec::from(0x2222))
+    }
+
+    #[test]
+    fn block_counter_tracks_keystream() {
+        let mut rabbit = rabbit();
+        assert_eq!(rabbit.block_counter(), 0);
+        rabbit.encrypt_inplace(&mut [0; 1]);
+        assert_eq!(rabbit.block_counter(), 1);
+        rabbit.encrypt_inplace(&mut [0; 15]);
+        assert_eq!(rabbit.block_counter(), 1);
+        rabbit.encrypt_inplace(&mut [0; 17]);
+        assert_eq!(rabbit.block_counter(), 3);
+        rabbit.reset();
+        assert_eq!(rabbit.block_counter(), 0);
+    }
+
+    #[test]
+    fn error_on_overrun() {
+        let mut limited = Limited::new(rabbit(), Limit::Blocks(2), OnLimit::Error);
+        let mut data = [0; 20];
+        assert_eq!(limited.encrypt_inplace(&mut data), Ok(()));
+        assert_eq!(limited.remaining(), 12);
+
+        let mut data = [0; 13];
+        assert_eq!(limited.encrypt_inplace(&mut data), Err(Error::KeystreamLimitReached));
+        assert_eq!(data, [0; 13]);
+        assert_eq!(limited.remaining(), 12);
+
+        let mut expected = [0; 32];
+        rabbit().encrypt_inplace(&mut expected);
+        let mut data = [0; 12];
+        assert_eq!(limited.encrypt_inplace(&mut data), Ok(()));
+        assert_eq!(&data[..], &expected[20..]);
+        assert_eq!(limited.encrypt_inplace(&mut []), Ok(()));
+        assert_eq!(limited.encrypt_inplace(&mut [0]), Err(Error::KeystreamLimitReached));
+    }
+
+    #[test]
+    fn rekey_through_ratchet() {
+        let mut limited = Limited::new(rabbit(), Limit::Bytes(10), OnLimit::Rekey);
+        let mut data = [0; 25];
+        assert_eq!(limited.encrypt_inplace(&mut data), Ok(()));
+        assert_eq!(limited.rekeys(), 2);
+        assert_eq!(limited.remaining(), 5);
+
+        let mut plain = rabbit();
+        let mut expected = [0; 10];
+        plain.encrypt_inplace(&mut expected);
+        assert_eq!(&data[..10], &expected[..]);
+
+        let mut key = [0; 16];
+        let mut ratchet = rabbit();
+        ratchet.encrypt_inplace(&mut [0; 16]);
+        ratchet.encrypt_inplace(&mut key);
+        let mut ratchet = Rabbit::new(&Key::from(key));
+        let mut expected = [0; 10];
+        ratchet.encrypt_inplace(&mut expected);
+        assert_eq!(&data[10..20], &expected[..]);
+
+        let mut decrypt = Limited::new(rabbit(), Limit::Bytes(10), OnLimit::Rekey);
+        for chunk in data.chunks_mut(3) {
+            assert_eq!(decrypt.decrypt_inplace(chunk), Ok(()));
+        }
+        assert_eq!(data, [0; 25]);
+    }
+
+    #[test]
+    fn zero_limit() {
+        let mut limited = Limited::new(rabbit(), Limit::Bytes(0), OnLimit::Rekey);
+        assert_eq!(limited.encrypt_inplace(&mut [0]), Err(Error::KeystreamLimitReached));
+        assert_eq!(limited.encrypt_inplace(&mut []), Ok(()));
+    }
+}
@@ -0,0 +1,437 @@
+//! Parallel encryption with `rayon`.
+//!
+//! Data is split into segments of `segment_size` bytes (the last one may be shorter).
+//! Each message gets its own segment key: first 16 bytes of HMAC-SHA256 keyed with the
+//! key over `"rabbit parallel v1"` and the base IV. Segment `i` is encrypted with
+//! `Rabbit::new_iv(segment_key, i)`, where `i` is taken as a little-endian `u64` IV.
+//! Segments are independent, so they are processed on all `rayon` threads. Both sides
+//! must use the same segment size.
+//!
+//! Because of the segment key, output is unrelated to sequential keystream of
+//! `Rabbit::new_iv(key, base_iv)`, and messages with different base IVs don't share
+//! keystream, consecutive ones from `IvSequence` included. A base IV must still be used
+//! for one parallel message only.
+//!
+//! ```ignore
+//! let cipher = ParallelRabbit::new(&key, &base_iv);
+//! cipher.encrypt_inplace(&mut data);
+//!
+//! let mut writer = Writer::new(cipher, file);
+//! io::copy(&mut input, &mut writer)?;
+//! let file = writer.finish()?;
+//! ```
+
+use std::cmp;
+use std::io::{self, Read, Write};
+
+use hmac::{Hmac, Mac};
+use rayon::prelude::*;
+use sha2::Sha256;
+
+use super::{InitVec, Key, RabbitKey};
+
+/// Default segment size.
+pub const DEFAULT_SEGMENT_SIZE: usize = 64 * 1024;
+
+/// Label of segment key derivation.
+const SEGMENT_KEY_INFO: &[u8] = b"rabbit parallel v1";
+
+/// Segment key of message with `base_iv`.
+fn segment_key(key: &Key, base_iv: &InitVec) -> Key {
+    let mut mac = Hmac::<Sha256>::new_from_slice(&key[..]).expect("HMAC takes any key length");
+    mac.update(SEGMENT_KEY_INFO);
+    mac.update(&base_iv[..]);
+    Key::from(&mac.finalize().into_bytes()[..16])
+}
+
+/// Segment key and segment size of parallel encryption.
+#[derive(Clone)]
+pub struct ParallelRabbit {
+    key: RabbitKey,
+    segment_size: usize,
+}
+
+impl ParallelRabbit {
+    /// Parallel cipher with `DEFAULT_SEGMENT_SIZE`.
+    pub fn new(key: &Key, base_iv: &InitVec) -> ParallelRabbit {
+        ParallelRabbit {
+            key: RabbitKey::new(&segment_key(key, base_iv)),
+            segment_size: DEFAULT_SEGMENT_SIZE,
+        }
+    }
+
+    /// Sets segment size. Asserts that `size > 0`.
+    pub fn segment_size(mut self, size: usize) -> ParallelRabbit {
+        assert!(size > 0);
+        self.segment_size = size;
+        self
+    }
+
+    /// IV of segment `index` (under the segment key).
+    pub fn segment_iv(&self, index: u64) -> InitVec {
+        InitVec::from(index)
+    }
+
+    /// Encrypts and writes bytes of `data` to `buf`.
+    /// Asserts that `buf.len() >= data.len()`.
+    pub fn encrypt(&self, data: &[u8], buf: &mut [u8]) {
+        assert!(buf.len() >= data.len());
+        let buf = &mut buf[..data.len()];
+        buf.copy_from_slice(data);
+        self.encrypt_inplace(buf);
+    }
+
+    #[inline]
+    /// Decrypts and writes bytes of `data` to `buf`.
+    /// Asserts that `buf.len() >= data.len()`.
+    pub fn decrypt(&self, data: &[u8], buf: &mut [u8]) {
+        self.encrypt(data, buf)
+    }
+
+    /// Encrypts whole message `data` inplace.
+    pub fn encrypt_inplace(&self, data: &mut [u8]) {
+        self.apply_segments(0, data)
+    }
+
+    #[inline]
+    /// Decrypts whole message `data` inplace.
+    pub fn decrypt_inplace(&self, data: &mut [u8]) {
+        self.encrypt_inplace(data)
+    }
+
+    /// Applies keystream to `data` starting at the beginning of segment `first`.
+    fn apply_segments(&self, first: u64, data: &mut [u8]) {
+        data.par_chunks_mut(self.segment_size).enumerate().for_each(|(i, segment)| {
+            self.key.rabbit_iv(&self.segment_iv(first + i as u64)).encrypt_inplace(segment);
+        });
+    }
+
+    /// Bytes processed at once by `Reader` and `Writer`: one segment per thread.
+    fn batch_size(&self) -> usize {
+        self.segment_size * rayon::current_num_threads()
+    }
+}
+
+/// Encrypts (or decrypts) data written to it and writes result to the inner writer.
+///
+/// Data is buffered until a segment per thread is collected. `finish` must be called to
+/// write the last, partial segment. If the inner writer fails, encrypted data that wasn't
+/// written is kept and written first by the next call.
+pub struct Writer<W: Write> {
+    inner: W,
+    cipher: ParallelRabbit,
+    segment: u64,
+    buf: Vec<u8>,
+    /// Encrypted bytes at the beginning of `buf`, not written yet.
+    encrypted: usize,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(cipher: ParallelRabbit, inner: W) -> Writer<W> {
+        Writer {
+            buf: Vec::with_capacity(cipher.batch_size()),
+            inner,
+            cipher,
+            segment: 0,
+            encrypted: 0,
+        }
+    }
+
+    /// Writes remaining data and returns inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_segments(true)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    /// Writes encrypted bytes, dropping them from `buf` as soon as they are written.
+    fn write_encrypted(&mut self) -> io::Result<()> {
+        while self.encrypted > 0 {
+            match self.inner.write(&self.buf[..self.encrypted]) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    self.buf.drain(..n);
+                    self.encrypted -= n;
+                }
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes pending encrypted bytes, then encrypts and writes full buffered segments, or
+    /// all buffered bytes if `all` (end of data).
+    fn write_segments(&mut self, all: bool) -> io::Result<()> {
+        self.write_encrypted()?;
+        let segment_size = self.cipher.segment_size;
+        let len = if all { self.buf.len() } else { self.buf.len() / segment_size * segment_size };
+        self.cipher.apply_segments(self.segment, &mut self.buf[..len]);
+        self.segment += len.div_ceil(segment_size) as u64;
+        self.encrypted = len;
+        self.write_encrypted()
+    }
+}
+
+impl<W: Write> Write for Writer<W> {
+    /// Accepts all of `buf`. Full buffer is written before, so an error means that nothing
+    /// of `buf` was accepted.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.buf.len() >= self.cipher.batch_size() {
+            self.write_segments(false)?;
+        }
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    /// Writes full segments and flushes inner writer. Partial segment is kept until it is
+    /// full or `finish` is called.
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_segments(false)?;
+        self.inner.flush()
+    }
+}
+
+/// Reads from the inner reader and decrypts (or encrypts) a segment per thread at once.
+pub struct Reader<R: Read> {
+    inner: R,
+    cipher: ParallelRabbit,
+    segment: u64,
+    buf: Vec<u8>,
+    /// Bytes read from inner reader but not processed yet.
+    filled: usize,
+    /// Processed bytes at the beginning of `buf` and read position in them.
+    end: usize,
+    pos: usize,
+    eof: bool,
+}
+
+impl<R: Read> Reader<R> {
+    pub fn new(cipher: ParallelRabbit, inner: R) -> Reader<R> {
+        Reader {
+            buf: vec![0; cipher.batch_size()],
+            inner,
+            cipher,
+            segment: 0,
+            filled: 0,
+            end: 0,
+            pos: 0,
+            eof: false,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Reads whole batch (or up to end of data) and processes it. On error, data read so
+    /// far is kept for the next call.
+    fn fill(&mut self) -> io::Result<()> {
+        while self.filled < self.buf.len() {
+            match self.inner.read(&mut self.buf[self.filled..]) {
+                Ok(0) => {
+                    self.eof = true;
+                    break;
+                }
+                Ok(n) => self.filled += n,
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        self.cipher.apply_segments(self.segment, &mut self.buf[..self.filled]);
+        self.segment += (self.filled / self.cipher.segment_size) as u64;
+        self.end = self.filled;
+        self.pos = 0;
+        self.filled = 0;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.end {
+            if self.eof || buf.is_empty() {
+                return Ok(0);
+            }
+            self.fill()?;
+        }
+        let count = cmp::min(buf.len(), self.end - self.pos);
+        buf[..count].copy_from_slice(&self.buf[self.pos..self.pos + count]);
+        self.pos += count;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{self, Read, Write};
+
+    use super::{segment_key, ParallelRabbit, Reader, Writer};
+    use super::super::{InitVec, Key, Rabbit};
+
+    fn key() -> Key {
+        Key::from([0x66; 16])
+    }
+
+    fn data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 31) as u8).collect()
+    }
+
+    /// Sequential reference: each segment with its own `Rabbit`.
+    fn reference(base_iv: u64, segment_size: usize, data: &[u8]) -> Vec<u8> {
+        let key = segment_key(&key(), &InitVec::from(base_iv));
+        let mut out = data.to_vec();
+        for (i, segment) in out.chunks_mut(segment_size).enumerate() {
+            Rabbit::new_iv(&key, &InitVec::from(i as u64)).encrypt_inplace(segment);
+        }
+        out
+    }
+
+    /// Keystream of `len` bytes, parallel with `base_iv`.
+    fn parallel(base_iv: u64, len: usize) -> Vec<u8> {
+        let mut out = vec![0; len];
+        let cipher = ParallelRabbit::new(&key(), &InitVec::from(base_iv)).segment_size(64);
+        cipher.encrypt_inplace(&mut out);
+        out
+    }
+
+    #[test]
+    fn matches_sequential_segments() {
+        let cases = [(0, 16), (1, 16), (100, 16), (1000, 7), (100_000, 4096)];
+        for &(len, segment_size) in cases.iter() {
+            let cipher = ParallelRabbit::new(&key(), &InitVec::from(5)).segment_size(segment_size);
+            let mut encrypted = data(len);
+            cipher.encrypt_inplace(&mut encrypted);
+            assert_eq!(encrypted, reference(5, segment_size, &data(len)));
+
+            let mut decrypted = vec![0; len];
+            cipher.decrypt(&encrypted, &mut decrypted);
+            assert_eq!(decrypted, data(len));
+        }
+    }
+
+    #[test]
+    fn differs_from_sequential_keystream() {
+        let mut sequential = vec![0; 128];
+        Rabbit::new_iv(&key(), &InitVec::from(5)).encrypt_inplace(&mut sequential);
+        let parallel = parallel(5, 128);
+        assert_ne!(&parallel[..64], &sequential[..64]);
+        assert_ne!(&parallel[64..], &sequential[64..]);
+    }
+
+    #[test]
+    fn adjacent_base_ivs_dont_overlap() {
+        for &base_iv in [0, 5, u64::MAX].iter() {
+            let first = parallel(base_iv, 128);
+            let second = parallel(base_iv.wrapping_add(1), 128);
+            assert_ne!(&first[64..], &second[..64]);
+            assert_ne!(first, second);
+        }
+    }
+
+    #[test]
+    fn writer_matches_one_shot() {
+        let len = 50_000;
+        let cipher = ParallelRabbit::new(&key(), &InitVec::from(9)).segment_size(1000);
+        let expected = reference(9, 1000, &data(len));
+        for &write_size in [1, 999, 1000, 4097, len].iter() {
+            let mut writer = Writer::new(cipher.clone(), Vec::new());
+            for piece in data(len).chunks(write_size) {
+                writer.write_all(piece).unwrap();
+                if write_size == 4097 {
+                    writer.flush().unwrap();
+                }
+            }
+            assert_eq!(writer.finish().unwrap(), expected, "write size {}", write_size);
+        }
+    }
+
+    /// Writer that takes at most 777 bytes per call and fails every third call.
+    struct FailingWriter {
+        data: Vec<u8>,
+        calls: usize,
+    }
+
+    impl Write for FailingWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.calls += 1;
+            if self.calls.is_multiple_of(3) {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            let count = buf.len().min(777);
+            self.data.extend_from_slice(&buf[..count]);
+            Ok(count)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn writer_retries_after_errors() {
+        let len = 50_000;
+        let cipher = ParallelRabbit::new(&key(), &InitVec::from(9)).segment_size(1000);
+        let mut writer = Writer::new(cipher, FailingWriter { data: Vec::new(), calls: 0 });
+        let data = data(len);
+        let mut rest = &data[..];
+        while !rest.is_empty() {
+            // Failed call accepts nothing, so writing the same bytes again doesn't repeat them.
+            match writer.write(&rest[..rest.len().min(3000)]) {
+                Ok(n) => rest = &rest[n..],
+                Err(err) => assert_eq!(err.kind(), io::ErrorKind::TimedOut),
+            }
+        }
+        while let Err(err) = writer.flush() {
+            assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        }
+        // Length is a multiple of segment size, nothing is left for `finish` to write.
+        assert_eq!(writer.finish().unwrap().data, reference(9, 1000, &data));
+    }
+
+    /// Reader that returns `Interrupted` and short reads.
+    struct Flaky<'a> {
+        data: &'a [u8],
+        calls: usize,
+    }
+
+    impl<'a> Read for Flaky<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.calls += 1;
+            if self.calls.is_multiple_of(3) {
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+            if self.calls.is_multiple_of(7) {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            let count = buf.len().min(self.data.len()).min(333);
+            buf[..count].copy_from_slice(&self.data[..count]);
+            self.data = &self.data[count..];
+            Ok(count)
+        }
+    }
+
+    #[test]
+    fn reader_decrypts() {
+        let len = 50_000;
+        let cipher = ParallelRabbit::new(&key(), &InitVec::from(9)).segment_size(1000);
+        let encrypted = reference(9, 1000, &data(len));
+
+        let mut decrypted = Vec::new();
+        Reader::new(cipher.clone(), &encrypted[..]).read_to_end(&mut decrypted).unwrap();
+        assert_eq!(decrypted, data(len));
+
+        // Errors other than `Interrupted` are returned, reading resumes without data loss.
+        let mut reader = Reader::new(cipher, Flaky { data: &encrypted, calls: 0 });
+        let mut decrypted = Vec::new();
+        let mut buf = [0; 1234];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => decrypted.extend_from_slice(&buf[..n]),
+                Err(err) => assert_eq!(err.kind(), io::ErrorKind::WouldBlock),
+            }
+        }
+        assert_eq!(decrypted, data(len));
+    }
+}
@@ -0,0 +1,194 @@
+//! Differential tests against the C implementation of `rabbit-reference` (`reference/c`,
+//! a dev-dependency). Run with `reference-c` feature, needs C compiler.
+
+extern crate rabbit_reference;
+
+use self::rabbit_reference::{ECRYPT_ivsetup, ECRYPT_keysetup, ECRYPT_process_bytes, Ctx,
+                             Instance, RABBIT_extract, RABBIT_next_state};
+use super::{counter_update, extract, next_state, setup_iv, setup_key, InitVec, Key, Rabbit,
+            State, A};
+
+fn to_instance(state: &State) -> Instance {
+    Instance {
+        x: state.state_vars,
+        c: state.counter_vars,
+        carry: state.carry_bit as u32,
+    }
+}
+
+fn to_state(instance: Instance) -> State {
+    State {
+        state_vars: instance.x,
+        counter_vars: instance.c,
+        carry_bit: instance.carry as u8,
+    }
+}
+
+fn reference(key: &Key, iv: Option<&InitVec>) -> Ctx {
+    let mut ctx = Ctx::default();
+    unsafe {
+        ECRYPT_keysetup(&mut ctx, key.as_ptr());
+        if let Some(iv) = iv {
+            ECRYPT_ivsetup(&mut ctx, iv.as_ptr());
+        }
+    }
+    ctx
+}
+
+fn reference_next_state(state: &State) -> State {
+    let mut instance = to_instance(state);
+    unsafe { RABBIT_next_state(&mut instance) };
+    to_state(instance)
+}
+
+/// xorshift64*, inputs only need to be arbitrary and reproducible.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn fill(&mut self, bytes: &mut [u8]) {
+        for byte in bytes.iter_mut() {
+            *byte = self.next_u64() as u8;
+        }
+    }
+
+    fn key(&mut self) -> Key {
+        let mut key = [0; 16];
+        self.fill(&mut key);
+        Key::from(key)
+    }
+
+    fn iv(&mut self) -> InitVec {
+        InitVec::from(self.next_u64())
+    }
+
+    fn state(&mut self) -> State {
+        let mut state = State::default();
+        for j in 0..8 {
+            state.state_vars[j] = self.next_u32();
+            state.counter_vars[j] = self.next_u32();
+        }
+        state.carry_bit = (self.next_u64() & 1) as u8;
+        state
+    }
+}
+
+#[test]
+fn setup_key_matches() {
+    let mut rng = Rng(0x0123456789ABCDEF);
+    for _ in 0..1000 {
+        let key = rng.key();
+        let mut state = State::default();
+        setup_key(&mut state, &key);
+        assert_eq!(state, to_state(reference(&key, None).master_ctx), "{:?}", key);
+    }
+}
+
+#[test]
+fn setup_iv_matches() {
+    let mut rng = Rng(0xFEDCBA9876543210);
+    for _ in 0..1000 {
+        let key = rng.key();
+        let iv = rng.iv();
+        let mut state = State::default();
+        setup_key(&mut state, &key);
+        setup_iv(&mut state, &iv);
+        assert_eq!(state, to_state(reference(&key, Some(&iv)).work_ctx), "{:?} {:?}", key, iv);
+    }
+}
+
+#[test]
+fn next_state_and_extract_match() {
+    let mut rng = Rng(0x5555AAAA5555AAAA);
+    for _ in 0..10000 {
+        let mut state = rng.state();
+        let expected = reference_next_state(&state);
+        next_state(&mut state);
+        assert_eq!(state, expected);
+
+        let mut s = [0u8; 16];
+        unsafe { RABBIT_extract(&to_instance(&state), s.as_mut_ptr()) };
+        assert_eq!(extract(&state), s);
+    }
+}
+
+#[test]
+fn counter_update_carry_edge_cases() {
+    let mut rng = Rng(0x0F0F0F0F0F0F0F0F);
+    // Counters right below, at and above the overflow point of each `A[j]` addition,
+    // with and without incoming carry.
+    let candidates = |a: u32| {
+        [0, 1, u32::MAX, u32::MAX - 1, !a, !a - 1, !a + 1, 0u32.wrapping_sub(a)]
+    };
+    for j in 0..8 {
+        for &counter in candidates(A[j]).iter() {
+            for &carry in [0, 1].iter() {
+                for _ in 0..16 {
+                    let mut state = rng.state();
+                    state.counter_vars[j] = counter;
+                    state.carry_bit = carry;
+                    if j > 0 {
+                        // Force carry into `j` from the previous counter, or its absence.
+                        state.counter_vars[j - 1] = if carry == 1 {
+                            0u32.wrapping_sub(A[j - 1])
+                        } else {
+                            0
+                        };
+                    }
+                    let expected = reference_next_state(&state);
+                    let mut counters = state.clone();
+                    counter_update(&mut counters);
+                    assert_eq!(counters.counter_vars, expected.counter_vars);
+                    assert_eq!(counters.carry_bit, expected.carry_bit);
+                    next_state(&mut state);
+                    assert_eq!(state, expected);
+                }
+            }
+        }
+    }
+    // Every counter at its overflow point propagates carry through the whole chain.
+    let mut state = State::default();
+    for (counter, a) in state.counter_vars.iter_mut().zip(A.iter()) {
+        *counter = !a;
+    }
+    state.carry_bit = 1;
+    let expected = reference_next_state(&state);
+    next_state(&mut state);
+    assert_eq!(state, expected);
+    assert_eq!(state.counter_vars, [0; 8]);
+    assert_eq!(state.carry_bit, 1);
+}
+
+#[test]
+fn encrypt_matches() {
+    let mut rng = Rng(0x1234567812345678);
+    for round in 0..500 {
+        let key = rng.key();
+        let iv = rng.iv();
+        let len = (rng.next_u64() % 1024) as usize;
+        let mut data = vec![0; len];
+        rng.fill(&mut data);
+
+        let mut expected = vec![0; len];
+        let (mut ctx, mut rabbit) = if round % 2 == 0 {
+            (reference(&key, Some(&iv)), Rabbit::new_iv(&key, &iv))
+        } else {
+            (reference(&key, None), Rabbit::new(&key))
+        };
+        unsafe { ECRYPT_process_bytes(&mut ctx, data.as_ptr(), expected.as_mut_ptr(), len) };
+
+        let mut actual = vec![0; len];
+        rabbit.encrypt(&data, &mut actual);
+        assert_eq!(actual, expected, "{:?} {:?} {}", key, iv, len);
+    }
+}
@@ -0,0 +1,220 @@
+//! Dudect-style timing leak tests, see `CONSTANT_TIME.md`.
+//!
+//! Each test times a function on inputs of two classes, fixed (class 0) and random
+//! (class 1), interleaved at random, then applies Welch's t-test to the timings. Large
+//! `|t|` means timing depends on input. Tests are ignored by default, run them in
+//! release mode on an idle machine:
+//!
+//! ```text
+//! cargo test --release --lib timing -- --ignored --test-threads 1
+//! ```
+
+use std::hint::black_box;
+
+use super::{counter_update_wide, g_func_wide, next_state, word, Key, Rabbit, State, A};
+
+/// Measurements per test.
+const SAMPLES: usize = 200_000;
+/// Calls per measurement.
+const BATCH: usize = 8;
+/// `|t|` above this fails a test. Dudect reports "definitely not constant time" from 10.
+const THRESHOLD: f64 = 10.0;
+
+/// xorshift64*, inputs only need to be arbitrary and reproducible.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn state(&mut self) -> State {
+        let mut state = State::default();
+        for j in 0..8 {
+            state.state_vars[j] = self.next_u32();
+            state.counter_vars[j] = self.next_u32();
+        }
+        state.carry_bit = (self.next_u64() & 1) as u8;
+        state
+    }
+
+    fn key(&mut self) -> Key {
+        let mut key = [0; 16];
+        for byte in key.iter_mut() {
+            *byte = self.next_u64() as u8;
+        }
+        Key::from(key)
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn ticks() -> u64 {
+    unsafe { ::std::arch::x86_64::_rdtsc() }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn ticks() -> u64 {
+    use std::time::Instant;
+    thread_local!(static EPOCH: Instant = Instant::now());
+    EPOCH.with(|epoch| epoch.elapsed().as_nanos() as u64)
+}
+
+/// Online mean and variance per class.
+#[derive(Default)]
+struct Welch {
+    n: [f64; 2],
+    mean: [f64; 2],
+    m2: [f64; 2],
+}
+
+impl Welch {
+    fn push(&mut self, class: usize, x: f64) {
+        self.n[class] += 1.0;
+        let delta = x - self.mean[class];
+        self.mean[class] += delta / self.n[class];
+        self.m2[class] += delta * (x - self.mean[class]);
+    }
+
+    fn t(&self) -> f64 {
+        let var0 = self.m2[0] / (self.n[0] - 1.0);
+        let var1 = self.m2[1] / (self.n[1] - 1.0);
+        (self.mean[0] - self.mean[1]) / (var0 / self.n[0] + var1 / self.n[1]).sqrt()
+    }
+}
+
+/// Largest `|t|` over timings cropped at several percentiles, as in dudect, since
+/// interrupts and cache misses only ever add time.
+fn max_t(samples: &[(usize, u64)]) -> f64 {
+    let mut sorted: Vec<u64> = samples.iter().map(|&(_, time)| time).collect();
+    sorted.sort_unstable();
+    let mut max = 0.0f64;
+    for &percentile in [0.5, 0.75, 0.9, 0.95, 0.99, 1.0].iter() {
+        let cutoff = sorted[((sorted.len() - 1) as f64 * percentile) as usize];
+        let mut welch = Welch::default();
+        for &(class, time) in samples.iter().filter(|&&(_, time)| time <= cutoff) {
+            welch.push(class, time as f64);
+        }
+        max = max.max(welch.t().abs());
+    }
+    max
+}
+
+/// Times `f` on `fixed` input and on inputs from `random`, returns `max_t`.
+fn measure<T, R, F>(seed: u64, fixed: T, mut random: R, f: F) -> f64
+where
+    T: Clone,
+    R: FnMut(&mut Rng) -> T,
+    F: Fn(&T),
+{
+    let mut rng = Rng(seed);
+    let inputs: Vec<(usize, T)> = (0..SAMPLES)
+        .map(|_| match rng.next_u64() & 1 {
+            0 => (0, fixed.clone()),
+            _ => (1, random(&mut rng)),
+        })
+        .collect();
+    let mut samples = Vec::with_capacity(SAMPLES);
+    for &(class, ref input) in inputs.iter() {
+        let start = ticks();
+        for _ in 0..BATCH {
+            f(black_box(input));
+        }
+        samples.push((class, ticks().wrapping_sub(start)));
+    }
+    max_t(&samples)
+}
+
+fn check(name: &str, t: f64) {
+    println!("{}: max |t| = {:.2}", name, t);
+    assert!(t < THRESHOLD, "{}: timing depends on input, max |t| = {:.2}", name, t);
+}
+
+#[test]
+#[ignore]
+fn harness_detects_leak() {
+    // Early-exit comparison, equal inputs take longest.
+    let leaky = |bytes: &[u8; 256]| {
+        for &byte in bytes.iter() {
+            if black_box(byte) != 0 {
+                break;
+            }
+        }
+    };
+    let random = |rng: &mut Rng| {
+        let mut bytes = [0; 256];
+        bytes[0] = rng.next_u64() as u8 | 1;
+        bytes
+    };
+    let t = measure(1, [0u8; 256], random, leaky);
+    println!("early exit comparison: max |t| = {:.2}", t);
+    assert!(t > THRESHOLD, "harness did not detect leak, max |t| = {:.2}", t);
+}
+
+#[test]
+#[ignore]
+fn g_func_is_constant_time() {
+    let random = |rng: &mut Rng| (rng.next_u32(), rng.next_u32());
+    check("g_func_wide", measure(2, (0, 0), random, |&(u, v)| {
+        black_box(g_func_wide(u, v));
+    }));
+    check("word::g_func", measure(3, (0, 0), random, |&(u, v)| {
+        black_box(word::g_func(u, v));
+    }));
+}
+
+#[test]
+#[ignore]
+fn counter_update_is_constant_time() {
+    // Every counter at its overflow point, carry runs through the whole chain.
+    let mut carry_chain = State::default();
+    for (counter, a) in carry_chain.counter_vars.iter_mut().zip(A.iter()) {
+        *counter = !a;
+    }
+    carry_chain.carry_bit = 1;
+    check("counter_update_wide", measure(4, carry_chain.clone(), Rng::state, |state| {
+        let mut state = state.clone();
+        counter_update_wide(&mut state);
+        black_box(state);
+    }));
+    check("word::counter_update", measure(5, carry_chain, Rng::state, |state| {
+        let mut state = state.clone();
+        word::counter_update(&mut state);
+        black_box(state);
+    }));
+}
+
+#[test]
+#[ignore]
+fn next_state_is_constant_time() {
+    check("next_state", measure(6, State::default(), Rng::state, |state| {
+        let mut state = state.clone();
+        next_state(&mut state);
+        black_box(state);
+    }));
+}
+
+#[test]
+#[ignore]
+fn encryption_is_constant_time() {
+    check("Rabbit::new + encrypt", measure(7, Key::from([0; 16]), Rng::key, |key| {
+        let mut data = [0u8; 64];
+        Rabbit::new(key).encrypt_inplace(&mut data);
+        black_box(data);
+    }));
+}
+
+#[test]
+#[ignore]
+fn key_comparison_is_constant_time() {
+    let reference = Key::from([0x5A; 16]);
+    check("Key::eq", measure(8, reference.clone(), Rng::key, |key| {
+        black_box(*key == reference);
+    }));
+}
@@ -0,0 +1,398 @@
+//! Data-driven test vector harness, see `tests/vectors/README.md`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "cryptopp")]
+use super::cryptopp;
+use super::{extract, next_state, setup_iv, setup_key, InitVec, Key, Rabbit, State, Stream};
+
+struct Vector {
+    name: String,
+    key: Key,
+    iv: Option<InitVec>,
+    /// Expected keystream bytes at given offsets.
+    ranges: Vec<(usize, Vec<u8>)>,
+    /// XOR of all 64-byte blocks of keystream (ECRYPT only).
+    xor_digest: Option<Vec<u8>>,
+}
+
+impl Vector {
+    fn new(name: String, key: Key) -> Vector {
+        Vector {
+            name,
+            key,
+            iv: None,
+            ranges: Vec::new(),
+            xor_digest: None,
+        }
+    }
+
+    /// Length of keystream needed to check this vector.
+    fn len(&self) -> usize {
+        let end = self.ranges.iter().map(|&(start, ref bytes)| start + bytes.len()).max().unwrap_or(0);
+        if self.xor_digest.is_some() {
+            end.div_ceil(64) * 64
+        } else {
+            end
+        }
+    }
+
+    fn rabbit(&self) -> Rabbit {
+        match self.iv {
+            Some(ref iv) => Rabbit::new_iv(&self.key, iv),
+            None => Rabbit::new(&self.key),
+        }
+    }
+
+    fn verify(&self, path: &str, keystream: &[u8]) {
+        for &(start, ref expected) in self.ranges.iter() {
+            assert_eq!(
+                &keystream[start..start + expected.len()], &expected[..],
+                "{}: {}: stream[{}..{}]", self.name, path, start, start + expected.len() - 1
+            );
+        }
+        if let Some(ref expected) = self.xor_digest {
+            let mut digest = [0u8; 64];
+            for block in keystream.chunks(64) {
+                for (d, b) in digest.iter_mut().zip(block.iter()) {
+                    *d ^= *b;
+                }
+            }
+            assert_eq!(&digest[..], &expected[..], "{}: {}: xor-digest", self.name, path);
+        }
+    }
+}
+
+fn vectors_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("vectors")
+}
+
+fn hex(text: &str) -> Vec<u8> {
+    let digits: Vec<u8> = text.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    assert!(digits.len().is_multiple_of(2), "odd number of hex digits: {}", text);
+    digits
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(::std::str::from_utf8(pair).unwrap(), 16).unwrap())
+        .collect()
+}
+
+/// Parses RFC 4503 Appendix A layout: `key`, `iv` and `S[i]` byte strings written
+/// most significant byte first.
+fn parse_rfc(name: &str, text: &str) -> Vec<Vector> {
+    let mut vectors: Vec<Vector> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('#') || !line.contains('=') {
+            continue;
+        }
+        let mut parts = line.splitn(2, '=');
+        let field = parts.next().unwrap().trim();
+        let value = parts.next().unwrap().trim().trim_start_matches('[').trim_end_matches(']');
+        let mut bytes = hex(value);
+        bytes.reverse();
+        match field {
+            "key" | "mkey" => {
+                let name = format!("{}: vector {}", name, vectors.len());
+                vectors.push(Vector::new(name, Key::from(&bytes[..])));
+            }
+            "iv" => vectors.last_mut().unwrap().iv = Some(InitVec::from(&bytes[..])),
+            _ if field.starts_with("S[") => {
+                let vector = vectors.last_mut().unwrap();
+                let start = vector.ranges.len() * 16;
+                vector.ranges.push((start, bytes));
+            }
+            _ => panic!("{}: unknown field {}", name, field),
+        }
+    }
+    vectors
+}
+
+/// Parses eSTREAM (ECRYPT) layout: `key`, `IV`, `stream[a..b]` and `xor-digest`
+/// hex strings in memory order, values may continue on following lines.
+fn parse_ecrypt(name: &str, text: &str) -> Vec<Vector> {
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.starts_with("Set ") && line.ends_with(':') {
+            fields.push(("Set".to_string(), line.trim_end_matches(':').to_string()));
+        } else if let Some(eq) = line.find(" = ") {
+            fields.push((line[..eq].trim().to_string(), line[eq + 3..].trim().to_string()));
+        } else if !line.is_empty() && line.bytes().all(|b| b.is_ascii_hexdigit()) {
+            if let Some(&mut (_, ref mut value)) = fields.last_mut() {
+                value.push_str(line);
+            }
+        }
+    }
+
+    let mut vectors: Vec<Vector> = Vec::new();
+    let mut title = String::new();
+    for (field, value) in fields {
+        if field == "Set" {
+            title = value;
+            continue;
+        }
+        let bytes = hex(&value);
+        match &field[..] {
+            "key" => {
+                let name = format!("{}: {}", name, title);
+                vectors.push(Vector::new(name, Key::from(&bytes[..])));
+            }
+            "IV" => vectors.last_mut().unwrap().iv = Some(InitVec::from(&bytes[..])),
+            "xor-digest" => vectors.last_mut().unwrap().xor_digest = Some(bytes),
+            _ if field.starts_with("stream[") => {
+                let range = field.trim_start_matches("stream[").trim_end_matches(']');
+                let mut bounds = range.split("..").map(|n| n.parse::<usize>().unwrap());
+                let (start, end) = (bounds.next().unwrap(), bounds.next().unwrap());
+                assert_eq!(end - start + 1, bytes.len(), "{}: {}", name, field);
+                vectors.last_mut().unwrap().ranges.push((start, bytes));
+            }
+            _ => panic!("{}: unknown field {}", name, field),
+        }
+    }
+    vectors
+}
+
+/// Decodes Crypto++ datum: hex, `"text"` and `rN` (repeat rest N times) tokens.
+fn cryptopp_datum(value: &str) -> Vec<u8> {
+    let (repeat, value) = match value.strip_prefix('r') {
+        Some(rest) => {
+            let (count, rest) = rest.split_once(' ').unwrap();
+            (count.parse().unwrap(), rest)
+        }
+        None => (1, value),
+    };
+    let bytes = match value.trim().strip_prefix('"') {
+        Some(text) => text.trim_end_matches('"').as_bytes().to_vec(),
+        None => hex(value),
+    };
+    bytes.repeat(repeat)
+}
+
+/// Parses Crypto++ `TestVectors` layout: `Name` (`Rabbit` or `RabbitWithIV`), `Key`,
+/// `IV`, `Plaintext` and `Ciphertext`, values may continue on following indented lines.
+/// As in Crypto++, fields keep their values until set again and every `Test` line makes a
+/// vector of current values.
+fn parse_cryptopp(name: &str, text: &str) -> Vec<Vector> {
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for line in text.lines() {
+        if line.starts_with(char::is_whitespace) && !line.trim().is_empty() {
+            let &mut (_, ref mut value) = fields.last_mut().unwrap();
+            value.push(' ');
+            value.push_str(line.trim());
+        } else if let Some((field, value)) = line.split_once(':') {
+            fields.push((field.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let mut vectors: Vec<Vector> = Vec::new();
+    let mut current: HashMap<String, String> = HashMap::new();
+    for (field, value) in fields {
+        if field != "Test" {
+            current.insert(field, value);
+            continue;
+        }
+        assert_eq!(value, "Encrypt", "{}: unsupported test", name);
+        let algorithm = &current["Name"];
+        let key = Key::from(&cryptopp_datum(&current["Key"])[..]);
+        let name = format!("{}: {} vector {}", name, algorithm, vectors.len());
+        let mut vector = Vector::new(name, key);
+        match &algorithm[..] {
+            "Rabbit" => {}
+            "RabbitWithIV" => vector.iv = Some(InitVec::from(&cryptopp_datum(&current["IV"])[..])),
+            _ => panic!("{}: unknown algorithm {}", vector.name, algorithm),
+        }
+        let plaintext = cryptopp_datum(&current["Plaintext"]);
+        let ciphertext = cryptopp_datum(&current["Ciphertext"]);
+        assert_eq!(plaintext.len(), ciphertext.len(), "{}", vector.name);
+        let keystream = plaintext.iter().zip(ciphertext.iter()).map(|(p, c)| p ^ c).collect();
+        vector.ranges.push((0, keystream));
+        vectors.push(vector);
+    }
+    vectors
+}
+
+/// Checks `vector` through the raw core, `Rabbit`, `Stream` and `Rabbit::skip`.
+fn check(vector: &Vector) {
+    let len = vector.len();
+
+    let mut state = State::default();
+    setup_key(&mut state, &vector.key);
+    if let Some(ref iv) = vector.iv {
+        setup_iv(&mut state, iv);
+    }
+    let mut keystream = Vec::with_capacity(len + 16);
+    while keystream.len() < len {
+        next_state(&mut state);
+        keystream.extend_from_slice(&extract(&state));
+    }
+    keystream.truncate(len);
+    vector.verify("raw core", &keystream);
+
+    let zeros = vec![0; len];
+    let mut keystream = vec![0; len];
+    vector.rabbit().encrypt(&zeros, &mut keystream);
+    vector.verify("Rabbit::encrypt", &keystream);
+
+    let mut keystream = vec![0; len];
+    let mut rabbit = vector.rabbit();
+    for chunk in keystream.chunks_mut(7) {
+        rabbit.encrypt_inplace(chunk);
+    }
+    vector.verify("Rabbit::encrypt_inplace", &keystream);
+
+    let mut stream = Stream::new(vector.rabbit(), Vec::new());
+    for chunk in zeros.chunks(13) {
+        stream.write_all(chunk).unwrap();
+    }
+    vector.verify("Stream::write", &stream.into_inner().1);
+
+    let mut keystream = Vec::new();
+    Stream::new(vector.rabbit(), &zeros[..]).read_to_end(&mut keystream).unwrap();
+    vector.verify("Stream::read", &keystream);
+
+    for &(start, ref expected) in vector.ranges.iter() {
+        let mut rabbit = vector.rabbit();
+        rabbit.skip(start as u64);
+        let mut keystream = vec![0; expected.len()];
+        rabbit.encrypt_inplace(&mut keystream);
+        assert_eq!(&keystream, expected, "{}: Rabbit::skip({})", vector.name, start);
+    }
+}
+
+#[test]
+fn rfc4503_appendix_a() {
+    let text = fs::read_to_string(vectors_dir().join("rfc4503.txt")).unwrap();
+    let vectors = parse_rfc("rfc4503.txt", &text);
+    assert_eq!(vectors.len(), 6);
+    assert_eq!(vectors.iter().filter(|vector| vector.iv.is_some()).count(), 3);
+    for vector in vectors.iter() {
+        assert_eq!(vector.len(), 48);
+        check(vector);
+    }
+}
+
+#[test]
+fn ecrypt_test_vector_files() {
+    let mut files = 0;
+    for entry in fs::read_dir(vectors_dir()).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|ext| ext != "test-vectors") {
+            continue;
+        }
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let vectors = parse_ecrypt(&name, &fs::read_to_string(&path).unwrap());
+        assert!(!vectors.is_empty(), "{}: no vectors", name);
+        for vector in vectors.iter() {
+            check(vector);
+        }
+        files += 1;
+    }
+    assert!(files > 0);
+}
+
+/// Checks `vector` through `rabbit::cryptopp` keying.
+#[cfg(feature = "cryptopp")]
+fn check_cryptopp_adapter(vector: &Vector) {
+    let mut rabbit = match vector.iv {
+        Some(ref iv) => cryptopp::rabbit_with_iv(&vector.key[..], &iv[..]).unwrap(),
+        None => cryptopp::rabbit(&vector.key[..]).unwrap(),
+    };
+    let mut keystream = vec![0; vector.len()];
+    rabbit.encrypt_inplace(&mut keystream);
+    vector.verify("cryptopp adapter", &keystream);
+}
+
+#[test]
+fn cryptopp_test_vector_files() {
+    let mut files = 0;
+    for entry in fs::read_dir(vectors_dir().join("cryptopp")).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|ext| ext != "txt") {
+            continue;
+        }
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let vectors = parse_cryptopp(&name, &fs::read_to_string(&path).unwrap());
+        assert!(!vectors.is_empty(), "{}: no vectors", name);
+        for vector in vectors.iter() {
+            check(vector);
+            #[cfg(feature = "cryptopp")]
+            check_cryptopp_adapter(vector);
+        }
+        files += 1;
+    }
+    assert!(files > 0);
+}
+
+#[test]
+fn cryptopp_layout() {
+    let text = "\
+AlgorithmType: SymmetricCipher
+Name: RabbitWithIV
+Comment: fields carry over to following tests
+Key: r16 0F
+IV: 0001020304050607
+Plaintext: \"ab\"
+Ciphertext: 00 01
+Test: Encrypt
+Name: Rabbit
+Plaintext: r3 FF
+Ciphertext: 0102
+    03
+Test: Encrypt
+";
+    let vectors = parse_cryptopp("inline", text);
+    assert_eq!(vectors.len(), 2);
+    assert_eq!(vectors[0].name, "inline: RabbitWithIV vector 0");
+    assert_eq!(*vectors[0].key, [0x0F; 16]);
+    assert_eq!(vectors[0].iv.as_ref().unwrap()[7], 0x07);
+    assert_eq!(vectors[0].ranges[0], (0, vec![b'a', b'b' ^ 1]));
+    assert_eq!(vectors[1].name, "inline: Rabbit vector 1");
+    assert_eq!(*vectors[1].key, [0x0F; 16]);
+    assert!(vectors[1].iv.is_none());
+    assert_eq!(vectors[1].ranges[0], (0, vec![0xFE, 0xFD, 0xFC]));
+}
+
+#[test]
+fn ecrypt_layout() {
+    let text = "\
+Set 6, vector#  3:
+                         key = 0F62B5085BAE0154A7FA4DA0F34699EC
+                          IV = 288FF65DC42B92F9
+               stream[0..3] = 00010203
+           stream[65472..65479] = 0405
+                                  060708090A0B
+                  xor-digest = 00
+                               11
+";
+    let vectors = parse_ecrypt("inline", text);
+    assert_eq!(vectors.len(), 1);
+    let vector = &vectors[0];
+    assert_eq!(vector.name, "inline: Set 6, vector#  3");
+    assert_eq!(vector.key[0], 0x0F);
+    assert_eq!(vector.iv.as_ref().unwrap()[7], 0xF9);
+    assert_eq!(vector.ranges[0], (0, vec![0, 1, 2, 3]));
+    assert_eq!(vector.ranges[1], (65472, vec![4, 5, 6, 7, 8, 9, 10, 11]));
+    assert_eq!(vector.xor_digest, Some(vec![0x00, 0x11]));
+    assert_eq!(vector.len(), 65536);
+}
+
+#[test]
+fn skip_matches_generated_keystream() {
+    let key = Key::from([0x3C; 16]);
+    let mut expected = vec![0; 1000];
+    Rabbit::new(&key).encrypt_inplace(&mut expected);
+    for &start in [0, 1, 15, 16, 17, 100, 511, 999].iter() {
+        for &first in [0, 5, 16].iter() {
+            let mut rabbit = Rabbit::new(&key);
+            let mut head = vec![0; first];
+            rabbit.encrypt_inplace(&mut head);
+            rabbit.skip(start as u64);
+            let mut rest = vec![0; 1000 - (first + start).min(1000)];
+            rabbit.encrypt_inplace(&mut rest);
+            assert_eq!(&rest[..], &expected[(first + start).min(1000)..]);
+            assert_eq!(rabbit.position(), 1000u64.max((first + start) as u64));
+        }
+    }
+}
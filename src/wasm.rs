@@ -0,0 +1,75 @@
+//! `wasm-bindgen` exports for JavaScript.
+//!
+//! Depend on `rabbit` with `wasm` feature from a `cdylib` crate (`pub use rabbit::wasm::*;`),
+//! build it for `wasm32-unknown-unknown` and process the module with `wasm-bindgen` (or
+//! build with `wasm-pack`). JavaScript gets class `Rabbit`:
+//!
+//! ```js
+//! const cipher = new Rabbit(key, iv); // Uint8Array, 16 bytes; optional IV, 8 bytes
+//! cipher.encrypt(data);               // Uint8Array, encrypted in place
+//! cipher.reset();
+//! cipher.decrypt(data);
+//! cipher.reinit(otherIv);
+//! cipher.free();
+//! ```
+//!
+//! Invalid key and IV lengths throw `Error`.
+
+use wasm_bindgen::prelude::*;
+
+use super::{InitVec, Key};
+
+fn key(key: &[u8]) -> Result<Key, JsError> {
+    if key.len() != 16 {
+        return Err(JsError::new(&format!("key must be 16 bytes, got {}", key.len())));
+    }
+    Ok(Key::from(key))
+}
+
+fn iv(iv: &[u8]) -> Result<InitVec, JsError> {
+    if iv.len() != 8 {
+        return Err(JsError::new(&format!("iv must be 8 bytes, got {}", iv.len())));
+    }
+    Ok(InitVec::from(iv))
+}
+
+/// `Rabbit` exported to JavaScript as `Rabbit`.
+#[wasm_bindgen(js_name = Rabbit)]
+pub struct WasmRabbit {
+    inner: super::Rabbit,
+}
+
+#[wasm_bindgen(js_class = Rabbit)]
+impl WasmRabbit {
+    /// Key setup only without `iv`, key and IV setup with it.
+    #[wasm_bindgen(constructor)]
+    pub fn new(key: &[u8], iv: Option<Box<[u8]>>) -> Result<WasmRabbit, JsError> {
+        let key = self::key(key)?;
+        let inner = match iv {
+            Some(iv) => super::Rabbit::new_iv(&key, &self::iv(&iv)?),
+            None => super::Rabbit::new(&key),
+        };
+        Ok(WasmRabbit { inner })
+    }
+
+    /// Encrypts `data` in place.
+    pub fn encrypt(&mut self, data: &mut [u8]) {
+        self.inner.encrypt_inplace(data)
+    }
+
+    /// Decrypts `data` in place.
+    pub fn decrypt(&mut self, data: &mut [u8]) {
+        self.inner.decrypt_inplace(data)
+    }
+
+    /// Restarts keystream of current mode.
+    pub fn reset(&mut self) {
+        self.inner.reset()
+    }
+
+    /// Restarts keystream from key setup followed by setup of `iv`.
+    pub fn reinit(&mut self, iv: &[u8]) -> Result<(), JsError> {
+        self.inner.reinit(&self::iv(iv)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+//! Word-oriented core for 32-bit targets: carry chain with `overflowing_add` and `g`
+//! function with single 32x32->64 multiplication, no `u64` additions or divisions.
+
+use super::{State, A};
+
+pub fn counter_update(state: &mut State) {
+    let mut carry = state.carry_bit != 0;
+    for (counter, a) in state.counter_vars.iter_mut().zip(A.iter()) {
+        let (sum, carry1) = counter.overflowing_add(*a);
+        let (sum, carry2) = sum.overflowing_add(carry as u32);
+        *counter = sum;
+        carry = carry1 | carry2;
+    }
+    state.carry_bit = carry as u8;
+}
+
+#[inline]
+pub fn g_func(u: u32, v: u32) -> u32 {
+    let x = u.wrapping_add(v);
+    let square = (x as u64) * (x as u64);
+    (square as u32) ^ ((square >> 32) as u32)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{counter_update, g_func};
+    use super::super::{counter_update_wide, g_func_wide, State, A};
+
+    /// xorshift64*, inputs only need to be arbitrary and reproducible.
+    fn rng(seed: &mut u64) -> u32 {
+        *seed ^= *seed >> 12;
+        *seed ^= *seed << 25;
+        *seed ^= *seed >> 27;
+        (seed.wrapping_mul(0x2545F4914F6CDD1D) >> 32) as u32
+    }
+
+    #[test]
+    fn g_func_matches_wide() {
+        let edges = [0, 1, 2, 0xFFFF, 0x10000, 0x7FFFFFFF, 0x80000000, 0xFFFFFFFE, 0xFFFFFFFF];
+        for &u in edges.iter() {
+            for &v in edges.iter() {
+                assert_eq!(g_func(u, v), g_func_wide(u, v), "{:08X} {:08X}", u, v);
+            }
+        }
+        let mut seed = 0x0123456789ABCDEF;
+        for _ in 0..100000 {
+            let (u, v) = (rng(&mut seed), rng(&mut seed));
+            assert_eq!(g_func(u, v), g_func_wide(u, v), "{:08X} {:08X}", u, v);
+        }
+    }
+
+    #[test]
+    fn counter_update_matches_wide() {
+        let mut seed = 0xFEDCBA9876543210;
+        for round in 0..100000 {
+            let mut state = State::default();
+            for (j, counter) in state.counter_vars.iter_mut().enumerate() {
+                *counter = match rng(&mut seed) % 4 {
+                    // Right at, below and above overflow point of `A[j]` addition.
+                    0 => !A[j],
+                    1 => (!A[j]).wrapping_sub(1),
+                    2 => (!A[j]).wrapping_add(1),
+                    _ => rng(&mut seed),
+                };
+            }
+            state.carry_bit = (round & 1) as u8;
+            let mut wide = state.clone();
+            counter_update(&mut state);
+            counter_update_wide(&mut wide);
+            assert_eq!(state, wide);
+        }
+        // Carry propagates through the whole chain.
+        let mut state = State::default();
+        for (counter, a) in state.counter_vars.iter_mut().zip(A.iter()) {
+            *counter = !a;
+        }
+        state.carry_bit = 1;
+        counter_update(&mut state);
+        assert_eq!(state.counter_vars, [0; 8]);
+        assert_eq!(state.carry_bit, 1);
+    }
+}
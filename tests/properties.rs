@@ -0,0 +1,181 @@
+//! Property-based tests of encryption invariants.
+#![cfg(not(target_arch = "wasm32"))]
+
+extern crate proptest;
+extern crate rabbit;
+
+use std::cmp;
+use std::io::{self, Read, Write};
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+use rabbit::{InitVec, Key, Limit, Limited, OnLimit, Rabbit, Session, Stream};
+
+fn rabbit(key: &[u8; 16], iv: Option<u64>) -> Rabbit {
+    match iv {
+        Some(iv) => Rabbit::new_iv(&Key::from(*key), &InitVec::from(iv)),
+        None => Rabbit::new(&Key::from(*key)),
+    }
+}
+
+/// Splits `data` at `points` (taken modulo `data.len() + 1`).
+fn split<'a>(data: &'a [u8], points: &[usize]) -> Vec<&'a [u8]> {
+    let mut points: Vec<usize> = points.iter().map(|p| p % (data.len() + 1)).collect();
+    points.sort();
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    for point in points.into_iter().chain(Some(data.len())) {
+        pieces.push(&data[start..point]);
+        start = point;
+    }
+    pieces
+}
+
+/// Reader that returns at most `max` bytes per `read`.
+struct Trickle<'a> {
+    data: &'a [u8],
+    max: usize,
+}
+
+impl<'a> Read for Trickle<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = cmp::min(cmp::min(buf.len(), self.max), self.data.len());
+        buf[..count].copy_from_slice(&self.data[..count]);
+        self.data = &self.data[count..];
+        Ok(count)
+    }
+}
+
+fn one_shot(key: &[u8; 16], iv: Option<u64>, data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0; data.len()];
+    rabbit(key, iv).encrypt(data, &mut out);
+    out
+}
+
+proptest! {
+    #[test]
+    fn roundtrip(key in any::<[u8; 16]>(), iv in any::<Option<u64>>(),
+                 data in vec(any::<u8>(), 0..2048)) {
+        let encrypted = one_shot(&key, iv, &data);
+        let mut decrypted = vec![0; data.len()];
+        rabbit(&key, iv).decrypt(&encrypted, &mut decrypted);
+        prop_assert_eq!(&decrypted, &data);
+
+        let mut inplace = encrypted.clone();
+        rabbit(&key, iv).decrypt_inplace(&mut inplace);
+        prop_assert_eq!(&inplace, &data);
+    }
+
+    #[test]
+    fn chunking_never_changes_output(key in any::<[u8; 16]>(), iv in any::<Option<u64>>(),
+                                     data in vec(any::<u8>(), 0..2048),
+                                     points in vec(any::<usize>(), 0..12)) {
+        let expected = one_shot(&key, iv, &data);
+
+        let mut cipher = rabbit(&key, iv);
+        let mut out = Vec::new();
+        for piece in split(&data, &points) {
+            let mut buf = vec![0; piece.len()];
+            cipher.encrypt(piece, &mut buf);
+            out.extend_from_slice(&buf);
+        }
+        prop_assert_eq!(&out, &expected);
+
+        let mut cipher = rabbit(&key, iv);
+        let mut out = Vec::new();
+        for piece in split(&data, &points) {
+            let mut buf = piece.to_vec();
+            cipher.encrypt_inplace(&mut buf);
+            out.extend_from_slice(&buf);
+        }
+        prop_assert_eq!(&out, &expected);
+    }
+
+    #[test]
+    fn stream_matches_encrypt(key in any::<[u8; 16]>(), iv in any::<Option<u64>>(),
+                              data in vec(any::<u8>(), 0..2048),
+                              points in vec(any::<usize>(), 0..12),
+                              max_read in 1usize..100) {
+        let expected = one_shot(&key, iv, &data);
+
+        let mut writer = Stream::new(rabbit(&key, iv), Vec::new());
+        for piece in split(&data, &points) {
+            writer.write_all(piece).unwrap();
+        }
+        prop_assert_eq!(&writer.into_inner().1, &expected);
+
+        let mut reader = Stream::new(rabbit(&key, iv), Trickle { data: &data, max: max_read });
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        prop_assert_eq!(&out, &expected);
+    }
+
+    #[test]
+    fn wrappers_match_encrypt(key in any::<[u8; 16]>(), iv in any::<u64>(),
+                              data in vec(any::<u8>(), 0..2048),
+                              points in vec(any::<usize>(), 0..12)) {
+        let expected = one_shot(&key, Some(iv), &data);
+
+        let mut session = Session::new(&Key::from(key), &InitVec::from(iv));
+        let mut limited = Limited::new(rabbit(&key, Some(iv)), Limit::Bytes(1 << 20),
+                                       OnLimit::Error);
+        let mut from_session = Vec::new();
+        let mut from_limited = Vec::new();
+        for piece in split(&data, &points) {
+            let mut buf = piece.to_vec();
+            session.encrypt_inplace(&mut buf);
+            from_session.extend_from_slice(&buf);
+            let mut buf = vec![0; piece.len()];
+            limited.encrypt(piece, &mut buf).unwrap();
+            from_limited.extend_from_slice(&buf);
+        }
+        prop_assert_eq!(&from_session, &expected);
+        prop_assert_eq!(&from_limited, &expected);
+    }
+
+    #[test]
+    fn reset_reproduces_keystream(key in any::<[u8; 16]>(), iv in any::<Option<u64>>(),
+                                  consumed in 0usize..1024, len in 0usize..1024) {
+        let mut expected = vec![0; len];
+        rabbit(&key, iv).encrypt_inplace(&mut expected);
+
+        let mut cipher = rabbit(&key, iv);
+        cipher.encrypt_inplace(&mut vec![0; consumed]);
+        cipher.reset();
+        let mut actual = vec![0; len];
+        cipher.encrypt_inplace(&mut actual);
+        prop_assert_eq!(&actual, &expected);
+    }
+
+    #[test]
+    fn reinit_reproduces_keystream(key in any::<[u8; 16]>(), first in any::<Option<u64>>(),
+                                   iv in any::<u64>(), consumed in 0usize..1024,
+                                   len in 0usize..1024) {
+        let mut expected = vec![0; len];
+        rabbit(&key, Some(iv)).encrypt_inplace(&mut expected);
+
+        let mut cipher = rabbit(&key, first);
+        cipher.encrypt_inplace(&mut vec![0; consumed]);
+        cipher.reinit(&InitVec::from(iv));
+        let mut actual = vec![0; len];
+        cipher.encrypt_inplace(&mut actual);
+        prop_assert_eq!(&actual, &expected);
+    }
+
+    #[test]
+    fn skip_matches_discarded_keystream(key in any::<[u8; 16]>(), iv in any::<Option<u64>>(),
+                                        first in 0usize..64, skip in 0u64..2048,
+                                        len in 0usize..256) {
+        let total = first + skip as usize + len;
+        let expected = one_shot(&key, iv, &vec![0; total]);
+
+        let mut cipher = rabbit(&key, iv);
+        let mut head = vec![0; first];
+        cipher.encrypt_inplace(&mut head);
+        cipher.skip(skip);
+        let mut tail = vec![0; len];
+        cipher.encrypt_inplace(&mut tail);
+        prop_assert_eq!(&head[..], &expected[..first]);
+        prop_assert_eq!(&tail[..], &expected[first + skip as usize..]);
+    }
+}
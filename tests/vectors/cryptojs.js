@@ -0,0 +1,70 @@
+// Generates `cryptojs.txt` with CryptoJS:
+//
+//     npm install crypto-js
+//     node cryptojs.js > cryptojs.txt
+
+const CryptoJS = require("crypto-js");
+
+const hex = (words) => words.toString(CryptoJS.enc.Hex);
+const text = "The quick brown fox jumps over the lazy dog";
+const plaintexts = ["", "00".repeat(48), Buffer.from(text).toString("hex")];
+
+const keyed = [
+    ["00000000000000000000000000000000", null],
+    ["000102030405060708090a0b0c0d0e0f", null],
+    ["000102030405060708090a0b0c0d0e0f", "0001020304050607"],
+    ["ac6f2c0d3e1b7e4a98c3d95b2f8e0612", "597e26c175f573c3"],
+];
+
+const passphrases = [
+    ["password", "0001020304050607"],
+    ["Secret Passphrase", "8d4c91a2e0f3b576"],
+    ["", "ffffffffffffffff"],
+];
+
+console.log("# CryptoJS Rabbit and RabbitLegacy vectors, generated by cryptojs.js.");
+
+for (const variant of ["Rabbit", "RabbitLegacy"]) {
+    for (const [key, iv] of keyed) {
+        for (const plaintext of plaintexts.slice(1)) {
+            const cfg = iv ? { iv: CryptoJS.enc.Hex.parse(iv) } : {};
+            const encrypted = CryptoJS[variant].encrypt(
+                CryptoJS.enc.Hex.parse(plaintext),
+                CryptoJS.enc.Hex.parse(key),
+                cfg,
+            );
+            console.log();
+            console.log(`variant    = ${variant}`);
+            console.log(`key        = ${key}`);
+            if (iv) {
+                console.log(`iv         = ${iv}`);
+            }
+            console.log(`plaintext  = ${plaintext}`);
+            console.log(`ciphertext = ${hex(encrypted.ciphertext)}`);
+        }
+    }
+
+    for (const [passphrase, salt] of passphrases) {
+        for (const plaintext of plaintexts) {
+            const params = CryptoJS.kdf.OpenSSL.execute(
+                passphrase, 128 / 32, 64 / 32, CryptoJS.enc.Hex.parse(salt),
+            );
+            const encrypted = CryptoJS[variant].encrypt(
+                CryptoJS.enc.Hex.parse(plaintext), params.key, { iv: params.iv },
+            );
+            const openssl = CryptoJS.format.OpenSSL.stringify(
+                CryptoJS.lib.CipherParams.create({ ciphertext: encrypted.ciphertext, salt: params.salt }),
+            );
+            const decrypted = CryptoJS[variant].decrypt(openssl, passphrase);
+            if (hex(decrypted) !== plaintext) {
+                throw new Error(`${variant} does not decrypt its own output`);
+            }
+            console.log();
+            console.log(`variant    = ${variant}`);
+            console.log(`passphrase = ${passphrase}`);
+            console.log(`salt       = ${salt}`);
+            console.log(`plaintext  = ${plaintext}`);
+            console.log(`openssl    = ${openssl}`);
+        }
+    }
+}
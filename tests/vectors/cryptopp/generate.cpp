@@ -0,0 +1,72 @@
+// Writes Crypto++ Rabbit and RabbitWithIV output in TestVectors layout:
+//
+//     c++ -std=c++11 generate.cpp -lcryptopp -o generate
+//     ./generate > cryptopp.txt
+
+#include <cstdio>
+#include <string>
+#include <vector>
+
+#include <cryptopp/config.h>
+#include <cryptopp/rabbit.h>
+
+using CryptoPP::byte;
+
+static std::string hex(const std::vector<byte> &bytes)
+{
+    static const char digits[] = "0123456789ABCDEF";
+    std::string out;
+    for (byte b : bytes) {
+        out += digits[b >> 4];
+        out += digits[b & 15];
+    }
+    return out;
+}
+
+static void print(const char *name, const std::vector<byte> &key, const std::vector<byte> *iv,
+                  const std::vector<byte> &plaintext, const std::vector<byte> &ciphertext)
+{
+    std::printf("AlgorithmType: SymmetricCipher\n");
+    std::printf("Name: %s\n", name);
+    std::printf("Source: Crypto++ %d, generate.cpp\n", CRYPTOPP_VERSION);
+    std::printf("Key: %s\n", hex(key).c_str());
+    if (iv) {
+        std::printf("IV: %s\n", hex(*iv).c_str());
+    }
+    std::printf("Plaintext: %s\n", hex(plaintext).c_str());
+    std::printf("Ciphertext: %s\n", hex(ciphertext).c_str());
+    std::printf("Test: Encrypt\n\n");
+}
+
+int main()
+{
+    // Lengths around the 16-byte block and a longer stream, encrypted in one call.
+    const size_t lengths[] = {1, 15, 16, 17, 100, 1000};
+    unsigned n = 0;
+    for (size_t len : lengths) {
+        for (int with_iv = 0; with_iv < 2; with_iv++, n++) {
+            std::vector<byte> key(16), iv(8), plaintext(len), ciphertext(len);
+            for (size_t i = 0; i < key.size(); i++) {
+                key[i] = (byte)(i * 0x11 + n * 0x3B);
+            }
+            for (size_t i = 0; i < iv.size(); i++) {
+                iv[i] = (byte)(0xF0 - i * 0x13 + n);
+            }
+            for (size_t i = 0; i < len; i++) {
+                plaintext[i] = (byte)(i * 7 + n);
+            }
+            if (with_iv) {
+                CryptoPP::RabbitWithIV::Encryption cipher;
+                cipher.SetKeyWithIV(key.data(), key.size(), iv.data(), iv.size());
+                cipher.ProcessData(ciphertext.data(), plaintext.data(), len);
+                print("RabbitWithIV", key, &iv, plaintext, ciphertext);
+            } else {
+                CryptoPP::Rabbit::Encryption cipher;
+                cipher.SetKey(key.data(), key.size());
+                cipher.ProcessData(ciphertext.data(), plaintext.data(), len);
+                print("Rabbit", key, nullptr, plaintext, ciphertext);
+            }
+        }
+    }
+    return 0;
+}
@@ -0,0 +1,91 @@
+//! Tests of `rabbit::wasm` on `wasm32-unknown-unknown`, run under Node.js:
+//!
+//! ```text
+//! cargo test --target wasm32-unknown-unknown --features wasm --test wasm
+//! ```
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+extern crate rabbit;
+extern crate wasm_bindgen_test;
+
+use rabbit::wasm::WasmRabbit;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+/// Key, IV, first 16 bytes of keystream.
+type Vector = ([u8; 16], Option<[u8; 8]>, [u8; 16]);
+
+/// RFC 4503 A.1 and A.2 vectors.
+const VECTORS: [Vector; 4] = [
+    (
+        [0; 16],
+        None,
+        [
+            0x02, 0xF7, 0x4A, 0x1C, 0x26, 0x45, 0x6B, 0xF5, 0xEC, 0xD6, 0xA5, 0x36, 0xF0, 0x54,
+            0x57, 0xB1,
+        ],
+    ),
+    (
+        [
+            0xAC, 0xC3, 0x51, 0xDC, 0xF1, 0x62, 0xFC, 0x3B, 0xFE, 0x36, 0x3D, 0x2E, 0x29, 0x13,
+            0x28, 0x91,
+        ],
+        None,
+        [
+            0x9C, 0x51, 0xE2, 0x87, 0x84, 0xC3, 0x7F, 0xE9, 0xA1, 0x27, 0xF6, 0x3E, 0xC8, 0xF3,
+            0x2D, 0x3D,
+        ],
+    ),
+    (
+        [0; 16],
+        Some([0; 8]),
+        [
+            0xED, 0xB7, 0x05, 0x67, 0x37, 0x5D, 0xCD, 0x7C, 0xD8, 0x95, 0x54, 0xF8, 0x5E, 0x27,
+            0xA7, 0xC6,
+        ],
+    ),
+    (
+        [0; 16],
+        Some([0x59, 0x7E, 0x26, 0xC1, 0x75, 0xF5, 0x73, 0xC3]),
+        [
+            0x6D, 0x7D, 0x01, 0x22, 0x92, 0xCC, 0xDC, 0xE0, 0xE2, 0x12, 0x00, 0x58, 0xB9, 0x4E,
+            0xCD, 0x1F,
+        ],
+    ),
+];
+
+fn cipher(key: &[u8; 16], iv: &Option<[u8; 8]>) -> WasmRabbit {
+    let iv = iv.map(|iv| iv.to_vec().into_boxed_slice());
+    WasmRabbit::new(key, iv).ok().unwrap()
+}
+
+#[wasm_bindgen_test]
+fn vectors() {
+    for (key, iv, stream) in VECTORS.iter() {
+        let mut cipher = cipher(key, iv);
+        let mut data = [0u8; 16];
+        cipher.encrypt(&mut data[..5]);
+        cipher.encrypt(&mut data[5..]);
+        assert_eq!(&data, stream);
+
+        cipher.reset();
+        cipher.decrypt(&mut data);
+        assert_eq!(data, [0; 16]);
+    }
+}
+
+#[wasm_bindgen_test]
+fn reinit() {
+    let (key, iv, stream) = &VECTORS[3];
+    let mut cipher = cipher(key, &None);
+    cipher.reinit(&iv.unwrap()).ok().unwrap();
+    let mut data = [0u8; 16];
+    cipher.encrypt(&mut data);
+    assert_eq!(&data, stream);
+}
+
+#[wasm_bindgen_test]
+fn invalid_lengths() {
+    assert!(WasmRabbit::new(&[0; 15], None).is_err());
+    assert!(WasmRabbit::new(&[0; 16], Some(vec![0; 7].into_boxed_slice())).is_err());
+    assert!(cipher(&[0; 16], &None).reinit(&[0; 9]).is_err());
+}